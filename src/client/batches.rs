@@ -0,0 +1,455 @@
+use std::collections::VecDeque;
+
+use futures::{stream, Stream};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    error::{parse_response, AnthropicError, ErrorResponse},
+    options::RequestOptions,
+    AnthropicClient, RequestBodyAnthropic, ResponseBodyAnthropic,
+};
+
+/// Client implementation for the Message Batches API.
+/// See <https://docs.anthropic.com/en/api/creating-message-batches>
+impl AnthropicClient {
+    /// Creates a batch of message requests processed asynchronously at a discount.
+    ///
+    /// # Arguments
+    /// * `requests` - The requests of the batch, each with a `custom_id` unique within the batch
+    ///
+    /// # Returns
+    /// The created [`Batch`], poll it with [`get_batch`](Self::get_batch) until
+    /// `processing_status` is `ended` then read the results with [`batch_results`](Self::batch_results).
+    ///
+    /// # Errors
+    /// Returns an error if:
+    /// * The params of a request fail validation, unless it is disabled in the config
+    /// * The HTTP request fails
+    /// * The response status is not 200 after retrying 429, 529 and 5xx statuses
+    /// * The response body cannot be parsed
+    pub async fn create_batch(&self, requests: Vec<BatchRequest>) -> Result<Batch, AnthropicError> {
+        self.create_batch_with_options(requests, &RequestOptions::default())
+            .await
+    }
+    /// Same as `create_batch` with per-request options such as beta headers or the version
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(url, status))
+    )]
+    pub async fn create_batch_with_options(
+        &self,
+        requests: Vec<BatchRequest>,
+        options: &RequestOptions,
+    ) -> Result<Batch, AnthropicError> {
+        for request in &requests {
+            self.check_body(&request.params)?;
+        }
+        let mut options = options.clone();
+        if let Some(request) = requests
+            .iter()
+            .find(|request| request.params.uses_files_api())
+        {
+            options = options.for_body(&request.params);
+        }
+        let body = CreateBatchRequest { requests };
+        let res = self
+            .send_with_retry(|| self.post("messages/batches", &options).json(&body))
+            .await?;
+        parse_response::<Batch>(res).await
+    }
+    /// Retrieves the current state of a batch.
+    ///
+    /// # Errors
+    /// Returns an error if the HTTP request fails, the status is not 200 or the body cannot be parsed
+    pub async fn get_batch(&self, batch_id: &str) -> Result<Batch, AnthropicError> {
+        self.get_batch_with_options(batch_id, &RequestOptions::default())
+            .await
+    }
+    /// Same as `get_batch` with per-request options such as beta headers or the version
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(url, status))
+    )]
+    pub async fn get_batch_with_options(
+        &self,
+        batch_id: &str,
+        options: &RequestOptions,
+    ) -> Result<Batch, AnthropicError> {
+        let path = format!("messages/batches/{}", batch_id);
+        let res = self.send_with_retry(|| self.get(&path, options)).await?;
+        parse_response::<Batch>(res).await
+    }
+    /// Lists the batches of the workspace, most recent first.
+    ///
+    /// # Errors
+    /// Returns an error if the HTTP request fails, the status is not 200 or the body cannot be parsed
+    pub async fn list_batches(&self) -> Result<ListBatchesBody, AnthropicError> {
+        self.list_batches_with_options(&RequestOptions::default())
+            .await
+    }
+    /// Same as `list_batches` with per-request options such as beta headers or the version
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(url, status))
+    )]
+    pub async fn list_batches_with_options(
+        &self,
+        options: &RequestOptions,
+    ) -> Result<ListBatchesBody, AnthropicError> {
+        let res = self
+            .send_with_retry(|| self.get("messages/batches", options))
+            .await?;
+        parse_response::<ListBatchesBody>(res).await
+    }
+    /// Cancels a batch, requests already being processed still complete.
+    ///
+    /// # Returns
+    /// The [`Batch`] with `processing_status` `canceling`
+    ///
+    /// # Errors
+    /// Returns an error if the HTTP request fails, the status is not 200 or the body cannot be parsed
+    pub async fn cancel_batch(&self, batch_id: &str) -> Result<Batch, AnthropicError> {
+        self.cancel_batch_with_options(batch_id, &RequestOptions::default())
+            .await
+    }
+    /// Same as `cancel_batch` with per-request options such as beta headers or the version
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(url, status))
+    )]
+    pub async fn cancel_batch_with_options(
+        &self,
+        batch_id: &str,
+        options: &RequestOptions,
+    ) -> Result<Batch, AnthropicError> {
+        let path = format!("messages/batches/{}/cancel", batch_id);
+        let res = self.send_with_retry(|| self.post(&path, options)).await?;
+        parse_response::<Batch>(res).await
+    }
+    /// Streams the results of an ended batch, one [`BatchResult`] per request.
+    ///
+    /// The results are sent as JSON lines and are not in the order of the requests,
+    /// use `custom_id` to match them.
+    ///
+    /// # Errors
+    /// Returns an error if the HTTP request fails or the status is not 200.
+    /// Items of the stream are errors if the connection drops or a line cannot be parsed.
+    pub async fn batch_results(
+        &self,
+        batch_id: &str,
+    ) -> Result<impl Stream<Item = Result<BatchResult, AnthropicError>>, AnthropicError> {
+        self.batch_results_with_options(batch_id, &RequestOptions::default())
+            .await
+    }
+    /// Same as `batch_results` with per-request options such as beta headers or the version
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(url, status))
+    )]
+    pub async fn batch_results_with_options(
+        &self,
+        batch_id: &str,
+        options: &RequestOptions,
+    ) -> Result<impl Stream<Item = Result<BatchResult, AnthropicError>>, AnthropicError> {
+        let path = format!("messages/batches/{}/results", batch_id);
+        let res = self.send_with_retry(|| self.get(&path, options)).await?;
+        let state = (Some(res), JsonLinesParser::default(), VecDeque::new());
+        Ok(stream::unfold(
+            state,
+            |(mut res, mut parser, mut pending)| async move {
+                loop {
+                    if let Some(result) = pending.pop_front() {
+                        return Some((result, (res, parser, pending)));
+                    }
+                    let response = res.as_mut()?;
+                    match response.chunk().await {
+                        Ok(Some(chunk)) => pending.extend(parser.push(&chunk)),
+                        Ok(None) => {
+                            res = None;
+                            pending.extend(parser.finish());
+                        }
+                        Err(e) => {
+                            res = None;
+                            pending.push_back(Err(e.into()));
+                        }
+                    }
+                }
+            },
+        ))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CreateBatchRequest {
+    requests: Vec<BatchRequest>,
+}
+
+/// A message request in a batch
+/// custom_id: Identifies the request in the results, must be unique within the batch
+/// params: The message request, the same body as for `get_message_completed`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchRequest {
+    pub custom_id: String,
+    pub params: RequestBodyAnthropic,
+}
+impl BatchRequest {
+    pub fn new(custom_id: impl Into<String>, params: RequestBodyAnthropic) -> Self {
+        Self {
+            custom_id: custom_id.into(),
+            params,
+        }
+    }
+}
+
+/// A message batch
+/// processing_status: Whether the batch is still being processed
+/// request_counts: Number of requests per state, only final once the batch has ended
+/// results_url: Url of the results, set once the batch has ended
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Batch {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub batch_type: String,
+    pub processing_status: ProcessingStatus,
+    pub request_counts: RequestCounts,
+    pub created_at: String,
+    pub expires_at: String,
+    pub ended_at: Option<String>,
+    pub cancel_initiated_at: Option<String>,
+    pub archived_at: Option<String>,
+    pub results_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingStatus {
+    InProgress,
+    Canceling,
+    Ended,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestCounts {
+    pub processing: u32,
+    pub succeeded: u32,
+    pub errored: u32,
+    pub canceled: u32,
+    pub expired: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListBatchesBody {
+    pub data: Vec<Batch>,
+    pub has_more: bool,
+    pub first_id: Option<String>,
+    pub last_id: Option<String>,
+}
+
+/// Result of one request of a batch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchResult {
+    pub custom_id: String,
+    pub result: BatchResultType,
+}
+
+/// Outcome of a batch request
+/// Succeeded: The message created for the request
+/// Errored: The request failed, e.g. its params were invalid
+/// Canceled: The batch was canceled before the request was processed
+/// Expired: The batch expired before the request was processed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchResultType {
+    Succeeded { message: Box<ResponseBodyAnthropic> },
+    Errored { error: ErrorResponse },
+    Canceled,
+    Expired,
+}
+
+/// Incremental parser for the JSON lines of batch results
+#[derive(Debug, Default)]
+struct JsonLinesParser {
+    buffer: Vec<u8>,
+}
+impl JsonLinesParser {
+    /// Feeds a chunk of bytes and returns every result completed by it
+    fn push(&mut self, chunk: &[u8]) -> Vec<Result<BatchResult, AnthropicError>> {
+        self.buffer.extend_from_slice(chunk);
+        let mut results = vec![];
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..pos + 1).collect();
+            results.extend(parse_line(&line));
+        }
+        results
+    }
+    /// Parses whatever is left in the buffer once the connection is closed
+    fn finish(&mut self) -> Option<Result<BatchResult, AnthropicError>> {
+        let line = std::mem::take(&mut self.buffer);
+        parse_line(&line)
+    }
+}
+
+fn parse_line(line: &[u8]) -> Option<Result<BatchResult, AnthropicError>> {
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return None;
+    }
+    Some(serde_json::from_slice(line).map_err(AnthropicError::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{Config, Messages};
+    use futures::StreamExt;
+    use std::time::Duration;
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn batch(status: &str, succeeded: u32, errored: u32) -> serde_json::Value {
+        serde_json::json!({
+            "id": "msgbatch_01",
+            "type": "message_batch",
+            "processing_status": status,
+            "request_counts": {
+                "processing": 2 - succeeded - errored,
+                "succeeded": succeeded,
+                "errored": errored,
+                "canceled": 0,
+                "expired": 0
+            },
+            "created_at": "2024-09-24T18:37:24.100435Z",
+            "expires_at": "2024-09-25T18:37:24.100435Z",
+            "ended_at": null,
+            "cancel_initiated_at": null,
+            "archived_at": null,
+            "results_url": null
+        })
+    }
+
+    const RESULTS: &str = r#"{"custom_id":"essay-2","result":{"type":"errored","error":{"type":"error","error":{"type":"invalid_request_error","message":"max_tokens: Field required"}}}}
+{"custom_id":"essay-1","result":{"type":"succeeded","message":{"id":"msg_1","type":"message","role":"assistant","model":"claude-3-5-sonnet-20241022","content":[{"type":"text","text":"Grade: A"}],"stop_reason":"end_turn","stop_sequence":null,"usage":{"input_tokens":11,"output_tokens":3}}}}
+"#;
+
+    #[tokio::test]
+    async fn test_create_and_poll_batch() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages/batches"))
+            .and(body_partial_json(serde_json::json!({
+                "requests": [
+                    {"custom_id": "essay-1", "params": {"max_tokens": 1000}},
+                    {"custom_id": "essay-2", "params": {"max_tokens": 1000}}
+                ]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(batch("in_progress", 0, 0)))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/messages/batches/msgbatch_01"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(batch("in_progress", 1, 0)))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/messages/batches/msgbatch_01"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(batch("ended", 1, 1)))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/messages/batches/msgbatch_01/results"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(RESULTS))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client =
+            AnthropicClient::new(Config::new("test-key".to_string(), server.uri())).unwrap();
+        let requests = ["essay-1", "essay-2"]
+            .into_iter()
+            .map(|id| {
+                BatchRequest::new(
+                    id,
+                    RequestBodyAnthropic {
+                        messages: vec![Messages::new_user_message_prompt(format!("Grade {}", id))],
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+        let mut batch = client.create_batch(requests).await.unwrap();
+        assert_eq!(batch.processing_status, ProcessingStatus::InProgress);
+        while batch.processing_status != ProcessingStatus::Ended {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            batch = client.get_batch(&batch.id).await.unwrap();
+        }
+        assert_eq!(batch.request_counts.succeeded, 1);
+        assert_eq!(batch.request_counts.errored, 1);
+
+        let results: Vec<BatchResult> = client
+            .batch_results(&batch.id)
+            .await
+            .unwrap()
+            .map(|result| result.unwrap())
+            .collect()
+            .await;
+        assert_eq!(results.len(), 2);
+        match &results[0].result {
+            BatchResultType::Errored { error } => {
+                assert_eq!(error.error.error_type, "invalid_request_error")
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        match &results[1].result {
+            BatchResultType::Succeeded { message } => assert_eq!(message.id, "msg_1"),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_and_cancel_batches() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/messages/batches"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": [batch("in_progress", 0, 0)],
+                "has_more": false,
+                "first_id": "msgbatch_01",
+                "last_id": "msgbatch_01"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages/batches/msgbatch_01/cancel"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(batch("canceling", 0, 0)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client =
+            AnthropicClient::new(Config::new("test-key".to_string(), server.uri())).unwrap();
+        let batches = client.list_batches().await.unwrap();
+        assert_eq!(batches.data.len(), 1);
+        let batch = client.cancel_batch(&batches.data[0].id).await.unwrap();
+        assert_eq!(batch.processing_status, ProcessingStatus::Canceling);
+    }
+
+    #[test]
+    fn test_parse_results_split_across_chunks() {
+        let mut parser = JsonLinesParser::default();
+        let mut results = vec![];
+        for chunk in RESULTS.as_bytes().chunks(5) {
+            results.extend(parser.push(chunk));
+        }
+        results.extend(parser.finish());
+        let ids: Vec<String> = results
+            .into_iter()
+            .map(|result| result.unwrap().custom_id)
+            .collect();
+        assert_eq!(ids, vec!["essay-2", "essay-1"]);
+    }
+}
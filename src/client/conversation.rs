@@ -0,0 +1,127 @@
+use super::{validation::ValidationError, MessageContent, Messages, Role};
+
+/// Multi-turn conversation that keeps the message order accepted by the API
+/// The first message must be from the user and roles must alternate
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Conversation {
+    messages: Vec<Messages>,
+}
+impl Conversation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Append a user message
+    /// Fails if the previous message is also from the user
+    pub fn push_user(
+        &mut self,
+        content: impl Into<MessageContent>,
+    ) -> Result<&mut Self, ValidationError> {
+        self.push(Messages::new(Role::User, content))
+    }
+    /// Append an assistant message
+    /// Fails if the conversation is empty or the previous message is also from the assistant
+    pub fn push_assistant(
+        &mut self,
+        content: impl Into<MessageContent>,
+    ) -> Result<&mut Self, ValidationError> {
+        self.push(Messages::new(Role::Assistant, content))
+    }
+    /// Append an assistant prefill the response continues from, see [`Messages::assistant_prefill`]
+    /// Trailing whitespace is trimmed, the prefill must be the last message of the request
+    /// Fails if the conversation is empty or the previous message is also from the assistant
+    pub fn push_assistant_prefill(
+        &mut self,
+        content: impl Into<String>,
+    ) -> Result<&mut Self, ValidationError> {
+        self.push(Messages::assistant_prefill(content.into()))
+    }
+    /// Append any message, e.g. one with content blocks or tool results
+    pub fn push(&mut self, message: Messages) -> Result<&mut Self, ValidationError> {
+        match self.messages.last() {
+            None if message.role != Role::User => {
+                return Err(ValidationError::invalid_value(
+                    "role",
+                    role_name(&message.role),
+                    "the first message must be from the user",
+                ))
+            }
+            Some(last) if last.role == message.role => {
+                return Err(ValidationError::invalid_value(
+                    "role",
+                    role_name(&message.role),
+                    "roles must alternate between user and assistant",
+                ))
+            }
+            _ => {}
+        }
+        self.messages.push(message);
+        Ok(self)
+    }
+    pub fn messages(&self) -> &[Messages] {
+        &self.messages
+    }
+    pub fn into_messages(self) -> Vec<Messages> {
+        self.messages
+    }
+}
+
+fn role_name(role: &Role) -> &'static str {
+    match role {
+        Role::User => "user",
+        Role::Assistant => "assistant",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alternation() {
+        let mut conversation = Conversation::new();
+        conversation
+            .push_user("What is the capital of France?")
+            .unwrap()
+            .push_assistant("Paris")
+            .unwrap()
+            .push_user("And of Germany?")
+            .unwrap();
+        let err = conversation.push_user("Hello?").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid role user: roles must alternate between user and assistant"
+        );
+        assert_eq!(conversation.messages().len(), 3);
+
+        let messages = conversation.into_messages();
+        let roles: Vec<Role> = messages.into_iter().map(|m| m.role).collect();
+        assert_eq!(roles, vec![Role::User, Role::Assistant, Role::User]);
+    }
+    #[test]
+    fn test_first_message_from_user() {
+        let mut conversation = Conversation::new();
+        let err = conversation.push_assistant("Hi").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid role assistant: the first message must be from the user"
+        );
+        assert!(conversation.messages().is_empty());
+        assert!(conversation
+            .push(Messages::new_user_message_prompt("Hi".to_string()))
+            .is_ok());
+    }
+    #[test]
+    fn test_assistant_prefill() {
+        let mut conversation = Conversation::new();
+        conversation
+            .push_user("Reply with a JSON object")
+            .unwrap()
+            .push_assistant_prefill("{\n  ")
+            .unwrap();
+        assert_eq!(
+            conversation.messages()[1].content,
+            MessageContent::String("{".to_string())
+        );
+        assert!(conversation.push_assistant_prefill("{").is_err());
+    }
+}
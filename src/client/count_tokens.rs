@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    error::{parse_response, AnthropicError},
+    options::RequestOptions,
+    tools::{Tool, ToolChoice},
+    AnthropicClient, Messages, RequestBodyAnthropic, SystemPrompt, Thinking,
+};
+
+/// Client implementation for the token counting endpoint.
+impl AnthropicClient {
+    /// Counts the input tokens of a message request without creating a message.
+    ///
+    /// # Arguments
+    /// * `body` - The request body that would be sent to `get_message_completed`.
+    ///   Only the fields accepted by the endpoint (model, messages, system, tools, tool_choice, thinking) are sent.
+    ///
+    /// # Returns
+    /// * `Result<CountTokensResponse, AnthropicError>` - A Result containing either:
+    ///   * `CountTokensResponse` - The number of input tokens of the request
+    ///   * `AnthropicError` - Any error that occurred during the request
+    ///
+    /// # Errors
+    /// Returns an error if:
+    /// * The HTTP request fails
+    /// * The response status is not 200 after retrying 429, 529 and 5xx statuses
+    /// * The response body cannot be parsed
+    pub async fn count_tokens(
+        &self,
+        body: &RequestBodyAnthropic,
+    ) -> Result<CountTokensResponse, AnthropicError> {
+        self.count_tokens_with_options(body, &RequestOptions::default())
+            .await
+    }
+    /// Same as `count_tokens` with per-request options such as beta headers or the version
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(model = %body.model, url, status))
+    )]
+    pub async fn count_tokens_with_options(
+        &self,
+        body: &RequestBodyAnthropic,
+        options: &RequestOptions,
+    ) -> Result<CountTokensResponse, AnthropicError> {
+        let request = CountTokensRequest::from(body);
+        let options = options.for_body(body);
+        let res = self
+            .send_with_retry(|| self.post("messages/count_tokens", &options).json(&request))
+            .await?;
+        parse_response::<CountTokensResponse>(res).await
+    }
+}
+
+/// Body of the token counting endpoint, a subset of [`RequestBodyAnthropic`]
+#[derive(Debug, Serialize)]
+struct CountTokensRequest<'a> {
+    model: &'a str,
+    messages: &'a [Messages],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<&'a SystemPrompt>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<&'a [Tool]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<&'a ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<&'a Thinking>,
+}
+impl<'a> From<&'a RequestBodyAnthropic> for CountTokensRequest<'a> {
+    fn from(body: &'a RequestBodyAnthropic) -> Self {
+        Self {
+            model: &body.model,
+            messages: &body.messages,
+            system: body.system.as_ref(),
+            tools: body.tools.as_deref(),
+            tool_choice: body.tool_choice.as_ref(),
+            thinking: body.thinking.as_ref(),
+        }
+    }
+}
+
+/// Response of the token counting endpoint
+/// input_tokens: The total number of tokens across the messages, system prompt and tools
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CountTokensResponse {
+    pub input_tokens: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_tokens_request_body() {
+        let body = RequestBodyAnthropic {
+            messages: vec![Messages::new_user_message_prompt("Hello".to_string())],
+            ..Default::default()
+        }
+        .with_system("Be concise.".to_string())
+        .with_stop_sequences(vec!["###".to_string()]);
+        let json = serde_json::to_value(CountTokensRequest::from(&body)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "model": "claude-3-5-sonnet-20241022",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "system": "Be concise."
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_count_tokens() {
+        dotenvy::dotenv().ok();
+        let client = AnthropicClient::default().unwrap();
+        let body = RequestBodyAnthropic {
+            messages: vec![Messages::new_user_message_prompt(
+                "What is the capital of France?".to_string(),
+            )],
+            ..Default::default()
+        };
+        match client.count_tokens(&body).await {
+            Ok(res) => {
+                println!("{:#?}", res);
+                assert!(res.input_tokens > 0);
+            }
+            Err(e) => {
+                panic!("{:?}", e);
+            }
+        }
+    }
+    #[tokio::test]
+    async fn test_count_tokens_retry() {
+        use crate::client::{test_support, Config};
+        use std::time::Duration;
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages/count_tokens"))
+            .respond_with(ResponseTemplate::new(529))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages/count_tokens"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"input_tokens": 9})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut config = Config::new("test-key".to_string(), server.uri());
+        config.set_retries(1, Duration::from_millis(1));
+        let client = AnthropicClient::new(config).unwrap();
+        let res = client.count_tokens(&test_support::body()).await.unwrap();
+        assert_eq!(res.input_tokens, 9);
+    }
+}
@@ -0,0 +1,347 @@
+use core::fmt;
+
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{retry::retry_after, validation::ValidationError};
+
+/// Error returned by the client methods
+/// RateLimited: 429, retry_after is read from the `retry-after` header in seconds
+/// InvalidRequest: 400, the request was rejected by the API
+/// Authentication: 401, the api key is missing or invalid
+/// Overloaded: 529, the API is temporarily overloaded
+/// Api: Any other error status returned by the API
+/// Transport: The HTTP request failed or the connection dropped
+/// Timeout: The request did not complete within the configured timeout
+/// Validation: The request failed local validation and was not sent
+/// Serialization: A body could not be serialized or parsed
+/// InvalidResponse: A success response could not be parsed, body holds its start
+/// IncompleteStream: A streamed message ended before its stop reason was sent
+#[derive(Debug)]
+pub enum AnthropicError {
+    RateLimited {
+        retry_after: Option<u64>,
+    },
+    InvalidRequest {
+        message: String,
+    },
+    Authentication,
+    Overloaded,
+    Api {
+        status: u16,
+        message: String,
+    },
+    Transport(reqwest::Error),
+    Timeout(reqwest::Error),
+    Validation(ValidationError),
+    Serialization(serde_json::Error),
+    InvalidResponse {
+        status: u16,
+        body: String,
+        source: serde_json::Error,
+    },
+    IncompleteStream,
+}
+impl AnthropicError {
+    /// Builds the error for a response with a non success status
+    pub(crate) async fn from_response(res: reqwest::Response) -> Self {
+        let status = res.status();
+        let retry_after = retry_after(res.headers());
+        let body = res.text().await.unwrap_or_default();
+        Self::from_parts(status, retry_after, &body)
+    }
+    /// Maps a status and an error body `{"type":"error","error":{"type","message"}}` to a variant
+    pub(crate) fn from_parts(status: StatusCode, retry_after: Option<u64>, body: &str) -> Self {
+        let error = serde_json::from_str::<ErrorResponse>(body)
+            .map(|res| res.error)
+            .ok();
+        let message = error
+            .as_ref()
+            .map(|error| error.message.clone())
+            .unwrap_or_else(|| body.to_string());
+        let error_type = error.as_ref().map(|error| error.error_type.as_str());
+        match (status.as_u16(), error_type) {
+            (429, _) | (_, Some("rate_limit_error")) => Self::RateLimited { retry_after },
+            (529, _) | (_, Some("overloaded_error")) => Self::Overloaded,
+            (401, _) | (_, Some("authentication_error")) => Self::Authentication,
+            (400, _) | (_, Some("invalid_request_error")) => Self::InvalidRequest { message },
+            (status, _) => Self::Api { status, message },
+        }
+    }
+}
+/// Longest part of a body kept in [`AnthropicError::InvalidResponse`]
+const MAX_BODY_SNIPPET_CHARS: usize = 512;
+
+/// Reads the body of a success response and parses it as JSON
+/// The body is read as text first so a parse failure can report what was received
+pub(crate) async fn parse_response<T: DeserializeOwned>(
+    res: reqwest::Response,
+) -> Result<T, AnthropicError> {
+    let status = res.status().as_u16();
+    let body = res.text().await?;
+    serde_json::from_str(&body).map_err(|source| {
+        let mut snippet: String = body.chars().take(MAX_BODY_SNIPPET_CHARS).collect();
+        if snippet.len() < body.len() {
+            snippet.push_str("...");
+        }
+        AnthropicError::InvalidResponse {
+            status,
+            body: snippet,
+            source,
+        }
+    })
+}
+impl fmt::Display for AnthropicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RateLimited {
+                retry_after: Some(retry_after),
+            } => write!(f, "Rate limited, retry after {}s", retry_after),
+            Self::RateLimited { retry_after: None } => write!(f, "Rate limited"),
+            Self::InvalidRequest { message } => write!(f, "Invalid request: {}", message),
+            Self::Authentication => write!(f, "Authentication failed"),
+            Self::Overloaded => write!(f, "API overloaded"),
+            Self::Api { status, message } => write!(f, "Error {}: {}", status, message),
+            Self::Transport(e) => write!(f, "Transport error: {}", e),
+            Self::Timeout(e) => write!(f, "Request timed out: {}", e),
+            Self::Validation(e) => write!(f, "Validation error: {}", e),
+            Self::Serialization(e) => write!(f, "Serialization error: {}", e),
+            Self::InvalidResponse {
+                status,
+                body,
+                source,
+            } => write!(
+                f,
+                "Invalid response with status {}: {}, body: {}",
+                status, source, body
+            ),
+            Self::IncompleteStream => write!(f, "The stream ended before the message was complete"),
+        }
+    }
+}
+impl std::error::Error for AnthropicError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Transport(e) | Self::Timeout(e) => Some(e),
+            Self::Validation(e) => Some(e),
+            Self::Serialization(e) | Self::InvalidResponse { source: e, .. } => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<reqwest::Error> for AnthropicError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Self::Timeout(e)
+        } else {
+            Self::Transport(e)
+        }
+    }
+}
+impl From<ValidationError> for AnthropicError {
+    fn from(e: ValidationError) -> Self {
+        Self::Validation(e)
+    }
+}
+impl From<serde_json::Error> for AnthropicError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Serialization(e)
+    }
+}
+
+/// Error returned when downloading an image with `Source::from_url`
+/// Transport: The HTTP request failed or the connection dropped
+/// Status: The server answered with a non success status
+/// UnsupportedContentType: The response is not a jpeg, png, gif or webp image
+/// TooLarge: The image is larger than the allowed size, `size` is the bytes received so far
+#[derive(Debug)]
+pub enum ImageDownloadError {
+    Transport(reqwest::Error),
+    Status(u16),
+    UnsupportedContentType(String),
+    TooLarge { size: usize, max_bytes: usize },
+}
+impl fmt::Display for ImageDownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(e) => write!(f, "Transport error: {}", e),
+            Self::Status(status) => write!(f, "Image download failed with status {}", status),
+            Self::UnsupportedContentType(content_type) => {
+                write!(f, "Unsupported image content type: {}", content_type)
+            }
+            Self::TooLarge { size, max_bytes } => write!(
+                f,
+                "Image of at least {} bytes exceeds the limit of {} bytes",
+                size, max_bytes
+            ),
+        }
+    }
+}
+impl std::error::Error for ImageDownloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Transport(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<reqwest::Error> for ImageDownloadError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Transport(e)
+    }
+}
+
+/// Error body returned by the API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    #[serde(rename = "type")]
+    pub response_type: String,
+    pub error: ErrorDetail,
+}
+/// Details of an error returned by the API
+/// error_type: e.g. `invalid_request_error`, `rate_limit_error`, `overloaded_error`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorDetail {
+    #[serde(rename = "type")]
+    pub error_type: String,
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{AnthropicClient, Config, Messages, RequestBodyAnthropic};
+    use std::time::Duration;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn error_body(error_type: &str, message: &str) -> String {
+        serde_json::json!({"type": "error", "error": {"type": error_type, "message": message}})
+            .to_string()
+    }
+
+    #[test]
+    fn test_error_from_status() {
+        let err = AnthropicError::from_parts(
+            StatusCode::TOO_MANY_REQUESTS,
+            Some(30),
+            &error_body("rate_limit_error", "Too many requests"),
+        );
+        assert!(matches!(
+            err,
+            AnthropicError::RateLimited {
+                retry_after: Some(30)
+            }
+        ));
+
+        let err = AnthropicError::from_parts(
+            StatusCode::BAD_REQUEST,
+            None,
+            &error_body("invalid_request_error", "max_tokens: field required"),
+        );
+        match err {
+            AnthropicError::InvalidRequest { message } => {
+                assert_eq!(message, "max_tokens: field required")
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        let err = AnthropicError::from_parts(
+            StatusCode::UNAUTHORIZED,
+            None,
+            &error_body("authentication_error", "invalid x-api-key"),
+        );
+        assert!(matches!(err, AnthropicError::Authentication));
+
+        let err = AnthropicError::from_parts(
+            StatusCode::from_u16(529).unwrap(),
+            None,
+            &error_body("overloaded_error", "Overloaded"),
+        );
+        assert!(matches!(err, AnthropicError::Overloaded));
+
+        let err =
+            AnthropicError::from_parts(StatusCode::BAD_GATEWAY, None, "<html>bad gateway</html>");
+        match err {
+            AnthropicError::Api { status, message } => {
+                assert_eq!(status, 502);
+                assert_eq!(message, "<html>bad gateway</html>");
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_error_converts_to_anyhow() {
+        fn call() -> Result<(), anyhow::Error> {
+            Err(AnthropicError::Overloaded)?;
+            Ok(())
+        }
+        assert_eq!(call().unwrap_err().to_string(), "API overloaded");
+    }
+    #[tokio::test]
+    async fn test_timeout() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+            .mount(&server)
+            .await;
+
+        let mut config = Config::new("test-key".to_string(), server.uri());
+        config.set_timeouts(Some(Duration::from_millis(50)), None);
+        let client = AnthropicClient::new(config).unwrap();
+        let body = RequestBodyAnthropic {
+            messages: vec![Messages::new_user_message_prompt("Hi".to_string())],
+            ..Default::default()
+        };
+        let err = client.get_message_completed(&body).await.unwrap_err();
+        assert!(matches!(err, AnthropicError::Timeout(_)), "{:?}", err);
+    }
+    #[tokio::test]
+    async fn test_invalid_response_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"id": "msg_1", "type": "message", "content": ["#,
+                "application/json",
+            ))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("x".repeat(2000)))
+            .mount(&server)
+            .await;
+
+        let client =
+            AnthropicClient::new(Config::new("test-key".to_string(), server.uri())).unwrap();
+        let body =
+            RequestBodyAnthropic::from_messages("claude-sonnet-4-20250514", 16, ["Hi"], None);
+        let err = client.get_message_completed(&body).await.unwrap_err();
+        match &err {
+            AnthropicError::InvalidResponse { status, body, .. } => {
+                assert_eq!(*status, 200);
+                assert_eq!(body, r#"{"id": "msg_1", "type": "message", "content": ["#);
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+        assert!(
+            err.to_string().contains(r#"body: {"id": "msg_1""#),
+            "{}",
+            err
+        );
+
+        // Long bodies are truncated
+        match client.get_message_completed(&body).await {
+            Err(AnthropicError::InvalidResponse { body, .. }) => {
+                assert_eq!(body, format!("{}...", "x".repeat(MAX_BODY_SNIPPET_CHARS)))
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use super::{ContentType, DocumentSource, MediaType, MessageContent, Messages, Role, Source};
+
+/// Builds a message from text and image blocks, kept in the order they are added
+/// Created with [`Messages::user`] or [`Messages::assistant`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageBuilder {
+    role: Role,
+    blocks: Vec<ContentType>,
+}
+impl MessageBuilder {
+    pub fn new(role: Role) -> Self {
+        Self {
+            role,
+            blocks: vec![],
+        }
+    }
+    /// Append a text block
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.blocks.push(ContentType::new_text(text.into()));
+        self
+    }
+    /// Append an image block with any source
+    pub fn image(mut self, source: Source) -> Self {
+        self.blocks.push(ContentType::new_image(source));
+        self
+    }
+    /// Append an image read from a file, see [`Source::from_file`]
+    pub fn image_file(self, path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        Ok(self.image(Source::from_file(path)?))
+    }
+    /// Append an image from raw bytes, base64 encoded
+    /// Empty bytes are reported by [`Source::validate`] before the request is sent
+    pub fn image_bytes(self, bytes: &[u8], media_type: MediaType) -> Self {
+        self.image(Source::new(STANDARD.encode(bytes), media_type))
+    }
+    /// Append an image the API downloads from a url
+    pub fn image_url(self, url: impl Into<String>) -> Self {
+        self.image(Source::new_url(url.into()))
+    }
+    /// Append a document block, e.g. a PDF
+    /// title: Title of the document, returned with citations
+    pub fn document(mut self, source: DocumentSource, title: Option<String>) -> Self {
+        self.blocks
+            .push(ContentType::new_document(source, title, None));
+        self
+    }
+    /// Append any content block
+    pub fn block(mut self, block: ContentType) -> Self {
+        self.blocks.push(block);
+        self
+    }
+    pub fn build(self) -> Messages {
+        Messages::new(self.role, MessageContent::ContentArray(self.blocks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_order() {
+        let png = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/pixel.png"
+        ))
+        .unwrap();
+        let message = Messages::user()
+            .text("Compare these images")
+            .image_file(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/pixel.png"
+            ))
+            .unwrap()
+            .image_bytes(&png, MediaType::Png)
+            .image_url("https://example.com/cat.png")
+            .text("Answer in one sentence")
+            .build();
+        assert_eq!(message.role, Role::User);
+
+        let MessageContent::ContentArray(blocks) = message.content else {
+            panic!("expected content blocks");
+        };
+        let source = Source::from_bytes(&png, MediaType::Png).unwrap();
+        assert_eq!(
+            blocks,
+            vec![
+                ContentType::new_text("Compare these images".to_string()),
+                ContentType::new_image(source.clone()),
+                ContentType::new_image(source),
+                ContentType::new_image(Source::new_url("https://example.com/cat.png".to_string())),
+                ContentType::new_text("Answer in one sentence".to_string()),
+            ]
+        );
+
+        assert!(Messages::user().image_file("missing.png").is_err());
+        let message = Messages::user().image_bytes(&[], MediaType::Png).build();
+        assert!(matches!(
+            &message.content,
+            MessageContent::ContentArray(blocks)
+                if matches!(&blocks[0], ContentType::Image(image) if image.source.validate().is_err())
+        ));
+        assert_eq!(
+            Messages::assistant().text("Hi").build().role,
+            Role::Assistant
+        );
+    }
+    #[test]
+    fn test_text_image_and_document() {
+        let source = Source::new_url("https://example.com/chart.png".to_string());
+        let message = Messages::user()
+            .text("Does the chart match the report?")
+            .image(source.clone())
+            .document(
+                DocumentSource::new_text("Revenue grew 12%".to_string()),
+                Some("Report".to_string()),
+            )
+            .build();
+        let expected = Messages::user_with_content(vec![
+            ContentType::new_text("Does the chart match the report?".to_string()),
+            ContentType::new_image(source),
+            ContentType::new_document(
+                DocumentSource::new_text("Revenue grew 12%".to_string()),
+                Some("Report".to_string()),
+                None,
+            ),
+        ]);
+        assert_eq!(message, expected);
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::json!({
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "Does the chart match the report?"},
+                    {"type": "image", "source": {"type": "url", "url": "https://example.com/chart.png"}},
+                    {
+                        "type": "document",
+                        "source": {"type": "text", "media_type": "text/plain", "data": "Revenue grew 12%"},
+                        "title": "Report"
+                    }
+                ]
+            })
+        );
+    }
+}
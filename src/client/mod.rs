@@ -1,38 +1,70 @@
+pub mod batches;
+pub mod conversation;
+pub mod count_tokens;
+pub mod error;
+pub mod message_builder;
+pub mod model_limits;
 pub mod models;
+pub mod options;
+pub mod retry;
+pub mod stream;
+/// Factories shared by the tests of the client modules
+#[cfg(test)]
+mod test_support;
+pub mod tools;
+pub mod validation;
 use core::fmt;
+use std::{collections::HashMap, path::Path, time::Duration};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
 
-use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
-use serde::{Deserialize, Serialize};
+use error::{parse_response, AnthropicError, ImageDownloadError};
+use message_builder::MessageBuilder;
+use model_limits::ModelLimits;
+use models::ClaudeModel;
+use options::RequestOptions;
+use reqwest::{
+    header::{HeaderName, HeaderValue, CONTENT_TYPE},
+    RequestBuilder, Response,
+};
+use retry::{DEFAULT_BASE_BACKOFF, DEFAULT_MAX_RETRIES};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tools::{Tool, ToolChoice};
+use validation::{check_range, ValidationError, Validator};
 
-const ANTHROPIC_VERSION: &str = "anthropic-version";
 const X_API_KEY: &str = "x-api-key";
+/// Headers set by the client itself, they cannot be replaced by the extra headers of the config
+const RESERVED_HEADERS: [&str; 4] = [
+    X_API_KEY,
+    "content-type",
+    "anthropic-version",
+    "anthropic-beta",
+];
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com";
 
+/// Value of the `anthropic-version` header
+/// Custom: Any other dated version, e.g. `2024-10-22`, sent as is
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub enum Version {
+    #[default]
     Latest,
     Initial,
-}
-impl Default for Version {
-    fn default() -> Self {
-        Self::Latest
-    }
+    Custom(String),
 }
 impl fmt::Display for Version {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Version::Latest => write!(f, "2023-06-01"),
             Version::Initial => write!(f, "2023-01-01"),
+            Version::Custom(version) => write!(f, "{}", version),
         }
     }
 }
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum ApiVersion {
+    #[default]
     V1,
 }
-impl Default for ApiVersion {
-    fn default() -> Self {
-        Self::V1
-    }
-}
 
 impl fmt::Display for ApiVersion {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -41,18 +73,53 @@ impl fmt::Display for ApiVersion {
         }
     }
 }
+/// Largest image accepted by the API, the default limit of `Source::from_url`
+pub const DEFAULT_MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+/// Largest width or height in pixels of an image accepted by the API
+pub const MAX_IMAGE_DIMENSION: u32 = 8000;
+/// Longest edge in pixels recommended by the API, larger images are downscaled by the API
+pub const RECOMMENDED_MAX_IMAGE_EDGE: u32 = 1568;
+/// max_tokens of the requests sent by `AnthropicClient::quick_prompt`
+pub const QUICK_PROMPT_MAX_TOKENS: u32 = 1024;
+/// Default timeout of a whole request, long enough for streaming responses
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(600);
+/// Default timeout for establishing a connection
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Configuration of the client
+/// max_retries: How many times a request failing with 429, 529 or 5xx is retried
+/// base_backoff: Delay before the first retry, doubled on every attempt
+/// timeout: Timeout of a whole request including reading the response, `None` waits forever
+/// connect_timeout: Timeout for establishing a connection, `None` waits forever
+/// beta_features: Beta features sent in the `anthropic-beta` header of every request
+/// headers: Extra headers sent with every request, e.g. `x-team-id` for a gateway,
+/// must not be one of the headers set by the client such as `x-api-key`
+/// validate: Whether request bodies are checked with [`RequestBodyAnthropic::validate`] before
+/// sending, disable it to let the API decide, e.g. for a parameter this crate rejects too strictly
 pub struct Config {
     pub api_key: String,
     pub api_url: String,
     pub version: Version,
     pub api_version: ApiVersion,
+    pub max_retries: u32,
+    pub base_backoff: Duration,
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    pub beta_features: Vec<String>,
+    pub headers: HashMap<String, String>,
+    pub validate: bool,
 }
 pub struct AnthropicClient {
-    api_key: String,
     api_url: String,
     version: Version,
     api_version: ApiVersion,
+    api_key: String,
     client: reqwest::Client,
+    max_retries: u32,
+    base_backoff: Duration,
+    beta_features: Vec<String>,
+    headers: HashMap<String, String>,
+    validate: bool,
 }
 impl Config {
     pub fn new(api_key: String, api_url: String) -> Self {
@@ -61,17 +128,61 @@ impl Config {
             api_url,
             version: Version::Latest,
             api_version: ApiVersion::V1,
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_backoff: DEFAULT_BASE_BACKOFF,
+            timeout: Some(DEFAULT_TIMEOUT),
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            beta_features: vec![],
+            headers: HashMap::new(),
+            validate: true,
         }
     }
     pub fn set_version(&mut self, version: Version) {
         self.version = version;
     }
+    /// Set the retry policy for requests failing with 429, 529 or 5xx
+    /// max_retries: 0 disables retries
+    /// base_backoff: Delay before the first retry, doubled on every attempt
+    pub fn set_retries(&mut self, max_retries: u32, base_backoff: Duration) {
+        self.max_retries = max_retries;
+        self.base_backoff = base_backoff;
+    }
+    /// Set the timeouts of the client
+    /// timeout: Timeout of a whole request including reading the response
+    /// connect_timeout: Timeout for establishing a connection
+    pub fn set_timeouts(&mut self, timeout: Option<Duration>, connect_timeout: Option<Duration>) {
+        self.timeout = timeout;
+        self.connect_timeout = connect_timeout;
+    }
+    /// Set the beta features sent with every request, e.g. `context-1m-2025-08-07`
+    /// Betas of [`RequestOptions`] are added to these
+    pub fn set_beta_features(&mut self, beta_features: Vec<String>) {
+        self.beta_features = beta_features;
+    }
+    /// Set extra headers sent with every request, e.g. the routing headers of a proxy
+    /// They are sent in addition to the auth, version and beta headers set by the client,
+    /// use [`Config::set_beta_features`] and [`Config::set_version`] to change those
+    pub fn set_headers(&mut self, headers: HashMap<String, String>) {
+        self.headers = headers;
+    }
+    /// Enable or disable the validation of request bodies before they are sent
+    /// Invalid bodies are then sent as is and rejected by the API with a 400 instead
+    pub fn set_validate(&mut self, validate: bool) {
+        self.validate = validate;
+    }
     pub fn new_with_version(api_key: String, api_url: String, version: Version) -> Self {
         Self {
             api_key,
             api_url,
             version,
             api_version: ApiVersion::V1,
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_backoff: DEFAULT_BASE_BACKOFF,
+            timeout: Some(DEFAULT_TIMEOUT),
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            beta_features: vec![],
+            headers: HashMap::new(),
+            validate: true,
         }
     }
     /// Create a new config with the api key and the api url
@@ -79,6 +190,9 @@ impl Config {
     /// Api url is set to https://api.anthropic.com
     /// version is set to the latest version
     /// api_version is set to v1
+    /// requests are retried twice with a base backoff of 500ms
+    /// requests time out after 600s, connecting times out after 10s
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Result<Self, anyhow::Error> {
         let api_key = std::env::var("ANTHROPIC_API_KEY")?;
         Ok(Self {
@@ -86,118 +200,653 @@ impl Config {
             api_url: ANTHROPIC_API_URL.to_string(),
             version: Version::Latest,
             api_version: ApiVersion::V1,
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_backoff: DEFAULT_BASE_BACKOFF,
+            timeout: Some(DEFAULT_TIMEOUT),
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            beta_features: vec![],
+            headers: HashMap::new(),
+            validate: true,
         })
     }
 }
+/// True if the header is set by the client, header names are case insensitive
+fn is_reserved_header(name: &str) -> bool {
+    RESERVED_HEADERS
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(name))
+}
 impl AnthropicClient {
-    pub fn new(config: Config) -> Self {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            ANTHROPIC_VERSION,
-            config.version.to_string().parse().unwrap(),
-        );
-        headers.insert(X_API_KEY, config.api_key.parse().unwrap());
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()
-            .unwrap();
+    /// Create a new client from the config
+    /// Fails if the api key or an extra header is not a valid header, e.g. when it ends with a newline,
+    /// or if an extra header is one set by the client such as `x-api-key` or `anthropic-version`
+    pub fn new(config: Config) -> Result<Self, anyhow::Error> {
+        HeaderValue::from_str(&config.api_key)?;
+        for (name, value) in &config.headers {
+            HeaderName::from_bytes(name.as_bytes())?;
+            HeaderValue::from_str(value)?;
+            if is_reserved_header(name) {
+                anyhow::bail!(
+                    "Header {} is set by the client and cannot be overridden",
+                    name
+                );
+            }
+        }
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = config.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(connect_timeout) = config.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        let client = builder.build()?;
 
+        Ok(Self::with_client(config, client))
+    }
+    /// Create a new client from the config using a preconfigured `reqwest::Client`,
+    /// e.g. one with a proxy, custom root certificates or connection pool settings
+    /// The auth, version and beta headers are still set on every request
+    /// The timeouts of the config are ignored, set them on the `reqwest::Client` instead
+    /// An invalid api key or extra header is reported when sending the first request,
+    /// extra headers set by the client itself are ignored
+    pub fn with_client(config: Config, client: reqwest::Client) -> Self {
         Self {
-            api_key: config.api_key,
             api_url: config.api_url,
+            api_key: config.api_key,
             client,
             version: config.version,
             api_version: config.api_version,
+            max_retries: config.max_retries,
+            base_backoff: config.base_backoff,
+            beta_features: config.beta_features,
+            headers: config.headers,
+            validate: config.validate,
         }
     }
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Result<Self, anyhow::Error> {
-        let config = Config::default()?;
-        let mut headers = HeaderMap::new();
-        headers.insert(ANTHROPIC_VERSION, config.version.to_string().parse()?);
-        headers.insert(X_API_KEY, config.api_key.parse()?);
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()
-            .unwrap();
-
-        Ok(Self {
-            api_key: config.api_key,
-            api_url: config.api_url,
-            client,
-            version: config.version,
-            api_version: config.api_version,
-        })
+        Self::new(Config::default()?)
     }
+    /// Set the `anthropic-version` sent with every following request
     pub fn set_version(&mut self, version: Version) {
         self.version = version;
     }
 
+    /// Builds the url of an endpoint as `{api_url}/{api_version}/{path}`, used by every request
+    /// api_url may contain a path prefix, e.g. for a proxy, and may end with a slash
     fn get_url(&self, path: &str) -> String {
-        format!("{}/{}/{}", self.api_url, self.api_version, path)
+        format!(
+            "{}/{}/{}",
+            self.api_url.trim_end_matches('/'),
+            self.api_version,
+            path.trim_start_matches('/')
+        )
+    }
+    /// Adds the api key, content type and extra headers, set per request so an injected client works
+    fn with_auth(&self, request: RequestBuilder) -> RequestBuilder {
+        let request = request
+            .header(X_API_KEY, self.api_key.as_str())
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        self.headers
+            .iter()
+            .filter(|(name, _)| !is_reserved_header(name))
+            .fold(request, |request, (name, value)| {
+                request.header(name.as_str(), value.as_str())
+            })
+    }
+    /// Builds a POST request to `path` with the version and beta headers of the options
+    /// The version is set per request so `set_version` applies to an existing client
+    fn post(&self, path: &str, options: &RequestOptions) -> RequestBuilder {
+        options.apply(
+            self.with_auth(self.client.post(self.get_url(path))),
+            &self.version,
+            &self.beta_features,
+        )
+    }
+    /// Builds a GET request to `path` with the version and beta headers of the options
+    fn get(&self, path: &str, options: &RequestOptions) -> RequestBuilder {
+        options.apply(
+            self.with_auth(self.client.get(self.get_url(path))),
+            &self.version,
+            &self.beta_features,
+        )
+    }
+    /// Sends a request
+    /// With the `tracing` feature the url and status are recorded on the current span
+    /// and the sizes of the request and response are logged as debug events
+    async fn send(&self, request: RequestBuilder) -> Result<Response, AnthropicError> {
+        let request = request.build()?;
+        #[cfg(feature = "tracing")]
+        {
+            tracing::Span::current().record("url", request.url().as_str());
+            tracing::debug!(
+                method = %request.method(),
+                body_bytes = request.body().and_then(|body| body.as_bytes()).map(|body| body.len()),
+                "sending request"
+            );
+        }
+        let res = self.client.execute(request).await?;
+        #[cfg(feature = "tracing")]
+        {
+            tracing::Span::current().record("status", res.status().as_u16());
+            tracing::debug!(
+                status = res.status().as_u16(),
+                content_length = res.content_length(),
+                "received response"
+            );
+        }
+        Ok(res)
     }
     pub async fn get_message_completed(
         &self,
-        body: RequestBodyAnthropic,
-    ) -> Result<ResponseBodyAnthropic, anyhow::Error> {
-        let res = self
-            .client
-            .post(self.get_url("messages"))
-            .body(serde_json::to_string(&body).unwrap())
-            .send()
-            .await?;
-        match res.status() {
-            reqwest::StatusCode::OK => {}
-            _ => {
-                return Err(anyhow::anyhow!(
-                    "Error: {}",
-                    res.text().await.unwrap_or("".to_string())
-                ));
-            }
-        }
-        let body = res.json::<ResponseBodyAnthropic>().await?;
+        body: &RequestBodyAnthropic,
+    ) -> Result<ResponseBodyAnthropic, AnthropicError> {
+        self.get_message_completed_with_options(body, &RequestOptions::default())
+            .await
+    }
+    /// Same as `get_message_completed` with per-request options such as beta headers or the version
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(model = %body.model, url, status))
+    )]
+    pub async fn get_message_completed_with_options(
+        &self,
+        body: &RequestBodyAnthropic,
+        options: &RequestOptions,
+    ) -> Result<ResponseBodyAnthropic, AnthropicError> {
+        let res = self.send_message(body, options).await?;
+        let body = parse_response::<ResponseBodyAnthropic>(res).await?;
         Ok(body)
     }
+    /// Send a single user prompt with default settings and return the text of the response
+    /// max_tokens is [`QUICK_PROMPT_MAX_TOKENS`], build a [`RequestBodyAnthropic`] for anything else
+    /// model: A [`ClaudeModel`] or any model id
+    pub async fn quick_prompt(
+        &self,
+        model: impl Into<String>,
+        prompt: &str,
+    ) -> Result<String, AnthropicError> {
+        let body =
+            RequestBodyAnthropic::from_messages(model, QUICK_PROMPT_MAX_TOKENS, [prompt], None);
+        Ok(self.get_message_completed(&body).await?.text())
+    }
+    /// Same as `get_message_completed` also returning the JSON of the response as received
+    /// Useful for debugging or reading fields this version of the crate doesn't model
+    pub async fn get_message_completed_raw(
+        &self,
+        body: &RequestBodyAnthropic,
+    ) -> Result<(ResponseBodyAnthropic, serde_json::Value), AnthropicError> {
+        let raw = self.get_message_completed_json(body).await?;
+        let message = serde_json::from_value(raw.clone())?;
+        Ok((message, raw))
+    }
+    /// Same as `get_message_completed` returning the JSON of the response without parsing it
+    /// Unlike `get_message_completed_raw` this succeeds when the response doesn't match
+    /// [`ResponseBodyAnthropic`], so the body can be inspected
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(model = %body.model, url, status))
+    )]
+    pub async fn get_message_completed_json(
+        &self,
+        body: &RequestBodyAnthropic,
+    ) -> Result<serde_json::Value, AnthropicError> {
+        let res = self.send_message(body, &RequestOptions::default()).await?;
+        parse_response(res).await
+    }
+    /// Validates the body unless validation is disabled in the config
+    fn check_body(&self, body: &RequestBodyAnthropic) -> Result<(), ValidationError> {
+        if self.validate {
+            body.validate()?;
+        }
+        Ok(())
+    }
+    /// Validates the body and sends it to the messages endpoint with retries
+    async fn send_message(
+        &self,
+        body: &RequestBodyAnthropic,
+        options: &RequestOptions,
+    ) -> Result<Response, AnthropicError> {
+        self.check_body(body)?;
+        let options = options.for_body(body);
+        self.send_with_retry(|| self.post("messages", &options).json(body))
+            .await
+    }
 }
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// Request body for the Anthropic API
+/// Optional fields are omitted from the JSON when `None`, the API rejects `null` for several of them
 /// model: The model to use for the completion
 /// max_tokens: The maximum number of tokens to generate
 /// messages: The messages to use for the completion
 /// temperature: The temperature to use for the completion
+/// system: The system prompt to use for the completion
+/// stop_sequences: Custom sequences that will cause the model to stop generating
+/// stream: Whether to stream the response using server-sent events
+/// top_p: Nucleus sampling, only the tokens within the top_p probability mass are considered
+/// top_k: Only sample from the top_k most likely tokens
+/// metadata: An object describing metadata about the request
+/// service_tier: Whether priority capacity may be used for the request
+/// tools: Definitions of tools the model may use
+/// tool_choice: How the model should use the provided tools
+/// thinking: Configuration of extended thinking
 pub struct RequestBodyAnthropic {
     pub model: String,
-    pub max_tokens: i32,
+    pub max_tokens: u32,
     pub messages: Vec<Messages>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<SystemPrompt>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<ServiceTier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Tool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<Thinking>,
 }
 impl Default for RequestBodyAnthropic {
     fn default() -> Self {
         Self {
-            model: "claude-3-5-sonnet-20241022".to_string(),
+            model: ClaudeModel::Claude35Sonnet.to_string(),
             max_tokens: 1000,
             messages: vec![],
             temperature: Some(0.1),
+            system: None,
+            stop_sequences: None,
+            stream: None,
+            top_p: None,
+            top_k: None,
+            metadata: None,
+            service_tier: None,
+            tools: None,
+            tool_choice: None,
+            thinking: None,
         }
     }
 }
 impl RequestBodyAnthropic {
+    /// Create a new request body
+    /// model: A [`ClaudeModel`] or any model id
     pub fn new(
-        model: String,
-        max_tokens: i32,
+        model: impl Into<String>,
+        max_tokens: u32,
         messages: Vec<Messages>,
         temperature: Option<f32>,
     ) -> Self {
         Self {
-            model,
+            model: model.into(),
             max_tokens,
             messages,
             temperature,
+            ..Default::default()
+        }
+    }
+    /// Same as `new` taking anything convertible to [`Messages`], e.g. `["Hi"]` or an iterator of `(Role, String)`
+    pub fn from_messages(
+        model: impl Into<String>,
+        max_tokens: u32,
+        messages: impl IntoIterator<Item = impl Into<Messages>>,
+        temperature: Option<f32>,
+    ) -> Self {
+        Self::new(
+            model,
+            max_tokens,
+            messages.into_iter().map(Into::into).collect(),
+            temperature,
+        )
+    }
+    /// Create a new request body with a system prompt
+    /// system: The system prompt that sets the assistant's behavior
+    pub fn new_with_system(
+        model: impl Into<String>,
+        max_tokens: u32,
+        messages: Vec<Messages>,
+        temperature: Option<f32>,
+        system: String,
+    ) -> Self {
+        Self::new(model, max_tokens, messages, temperature).with_system(system)
+    }
+    /// Start building a request body from the defaults, the body is its own builder:
+    /// every `with_*` setter and [`RequestBodyAnthropic::messages`] take and return it
+    /// e.g. `RequestBodyAnthropic::builder().messages(["Hi"]).with_max_tokens(100)`
+    pub fn builder() -> Self {
+        Self::default()
+    }
+    /// Set the messages on the request body
+    /// messages: Anything convertible to [`Messages`], e.g. `&str` for user messages or `(Role, String)`
+    pub fn messages(mut self, messages: impl IntoIterator<Item = impl Into<Messages>>) -> Self {
+        self.messages = messages.into_iter().map(Into::into).collect();
+        self
+    }
+    /// Set the maximum number of tokens to generate on the request body
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+    /// Set the model on the request body
+    /// model: A [`ClaudeModel`] or any model id
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+    /// Set a plain text system prompt on the request body
+    /// system: The system prompt that sets the assistant's behavior
+    pub fn with_system(mut self, system: String) -> Self {
+        self.system = Some(SystemPrompt::Text(system));
+        self
+    }
+    /// Set a system prompt made of content blocks on the request body
+    /// blocks: The text blocks that make up the system prompt
+    pub fn with_system_blocks(mut self, blocks: Vec<ContentType>) -> Self {
+        self.system = Some(SystemPrompt::Blocks(blocks));
+        self
+    }
+    /// Set a system prompt with a cached preamble and an uncached suffix on the request body
+    /// See [`SystemPrompt::cached_preamble`]
+    pub fn with_cached_system(
+        mut self,
+        preamble: impl Into<String>,
+        suffix: impl Into<String>,
+    ) -> Self {
+        self.system = Some(SystemPrompt::cached_preamble(preamble, suffix));
+        self
+    }
+    /// Set the stop sequences on the request body
+    /// stop_sequences: Sequences that will cause the model to stop generating
+    /// The sequence that was hit is returned in `ResponseBodyAnthropic::stop_sequence`
+    pub fn with_stop_sequences(mut self, stop_sequences: Vec<String>) -> Self {
+        self.stop_sequences = Some(stop_sequences);
+        self
+    }
+    /// Set the nucleus sampling probability mass on the request body
+    /// top_p: Must be between 0.0 and 1.0
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+    /// Set the top_k sampling parameter on the request body
+    /// top_k: Must be greater than 0
+    pub fn with_top_k(mut self, top_k: u32) -> Self {
+        self.top_k = Some(top_k);
+        self
+    }
+    /// Set the end-user identifier sent in the request metadata
+    /// user_id: An opaque identifier for the user, such as a uuid or hash, used for abuse detection
+    pub fn with_user_id(mut self, user_id: impl Into<String>) -> Self {
+        self.metadata = Some(Metadata {
+            user_id: Some(user_id.into()),
+        });
+        self
+    }
+    /// Set the service tier on the request body
+    pub fn with_service_tier(mut self, service_tier: ServiceTier) -> Self {
+        self.service_tier = Some(service_tier);
+        self
+    }
+    /// Set the tools the model may use
+    /// tools: Definitions of the tools
+    pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+    /// Set how the model should use the provided tools
+    /// tool_choice: Auto, any, a specific tool or none
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+    /// Enable extended thinking
+    /// budget_tokens: Tokens the model may use to think, at least 1024 and less than max_tokens
+    /// temperature and top_k are cleared, the API rejects them with thinking enabled
+    pub fn with_thinking(mut self, budget_tokens: u32) -> Self {
+        self.thinking = Some(Thinking::Enabled { budget_tokens });
+        self.temperature = None;
+        self.top_k = None;
+        self
+    }
+    /// Check the request body for values the API would reject
+    /// This is called before every request so errors are reported without hitting the network
+    /// Every problem is reported, as [`ValidationError::Multiple`] when there is more than one
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let mut validator = Validator::default();
+        self.check_messages(&mut validator);
+        if self.model.trim().is_empty() {
+            validator.push(ValidationError::invalid_value(
+                "model",
+                "\"\"",
+                "must not be empty",
+            ));
+        }
+        if self.max_tokens == 0 {
+            validator.push(ValidationError::invalid_value(
+                "max_tokens",
+                0,
+                "must be greater than 0",
+            ));
+        }
+        validator.check(check_range("temperature", self.temperature, 0.0, 1.0));
+        validator.check(check_range("top_p", self.top_p, 0.0, 1.0));
+        if self.top_k == Some(0) {
+            validator.push(ValidationError::invalid_value(
+                "top_k",
+                0,
+                "must be greater than 0",
+            ));
+        }
+        for stop_sequence in self.stop_sequences.iter().flatten() {
+            if stop_sequence.trim().is_empty() {
+                validator.push(ValidationError::invalid_value(
+                    "stop_sequences",
+                    format!("{:?}", stop_sequence),
+                    "must contain non-whitespace characters",
+                ));
+            }
+        }
+        if let Some(Thinking::Enabled { budget_tokens }) = self.thinking {
+            if budget_tokens < MIN_THINKING_BUDGET_TOKENS {
+                validator.push(ValidationError::invalid_value(
+                    "thinking.budget_tokens",
+                    budget_tokens,
+                    "must be at least 1024",
+                ));
+            }
+            if budget_tokens >= self.max_tokens {
+                validator.push(ValidationError::invalid_value(
+                    "thinking.budget_tokens",
+                    budget_tokens,
+                    &format!("must be less than max_tokens ({})", self.max_tokens),
+                ));
+            }
+            if let Some(temperature) = self.temperature.filter(|temperature| *temperature != 1.0) {
+                validator.push(ValidationError::invalid_value(
+                    "temperature",
+                    temperature,
+                    "must be 1 or unset when thinking is enabled",
+                ));
+            }
+            if let Some(top_k) = self.top_k {
+                validator.push(ValidationError::invalid_value(
+                    "top_k",
+                    top_k,
+                    "must be unset when thinking is enabled",
+                ));
+            }
+        }
+        self.check_images(&mut validator);
+        validator.finish()
+    }
+    /// Check every inline image against the size limits, see [`Source::validate`]
+    fn check_images(&self, validator: &mut Validator) {
+        for (index, message) in self.messages.iter().enumerate() {
+            let MessageContent::ContentArray(blocks) = &message.content else {
+                continue;
+            };
+            for (block_index, block) in blocks.iter().enumerate() {
+                for source in block.image_sources() {
+                    if let Err(ValidationError::InvalidValue { value, reason, .. }) =
+                        source.validate()
+                    {
+                        validator.push(ValidationError::invalid_content_block(
+                            index,
+                            block_index,
+                            &format!("image of {} {}", value, reason),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    /// True if a message references a file uploaded with the Files API
+    /// The files api beta is then sent with the request
+    pub fn uses_files_api(&self) -> bool {
+        self.messages
+            .iter()
+            .any(|message| message.content.references_file())
+    }
+    /// Check that there is at least one message, the first message is from the user
+    /// and no message has empty content or an empty text block
+    pub fn validate_messages(&self) -> Result<(), ValidationError> {
+        let mut validator = Validator::default();
+        self.check_messages(&mut validator);
+        validator.finish()
+    }
+    fn check_messages(&self, validator: &mut Validator) {
+        match self.messages.first() {
+            None => validator.push(ValidationError::invalid_value(
+                "messages",
+                "[]",
+                "at least one message is required",
+            )),
+            Some(message) if message.role != Role::User => validator.push(
+                ValidationError::invalid_message(0, "the first message must be from the user"),
+            ),
+            _ => {}
+        }
+        for (index, message) in self.messages.iter().enumerate() {
+            if message.content.is_empty() {
+                validator.push(ValidationError::invalid_message(
+                    index,
+                    "content must not be empty",
+                ));
+            } else if let MessageContent::ContentArray(blocks) = &message.content {
+                if blocks
+                    .iter()
+                    .any(|block| matches!(block, ContentType::Text(text) if text.text.is_empty()))
+                {
+                    validator.push(ValidationError::invalid_message(
+                        index,
+                        "text blocks must not be empty",
+                    ));
+                }
+            }
+        }
+        if let Some(message) = self.messages.last() {
+            let text = match &message.content {
+                MessageContent::String(text) => Some(text.as_str()),
+                MessageContent::ContentArray(blocks) => match blocks.last() {
+                    Some(ContentType::Text(text)) => Some(text.text.as_str()),
+                    _ => None,
+                },
+            };
+            if message.role == Role::Assistant
+                && text.is_some_and(|text| text.ends_with(char::is_whitespace))
+            {
+                validator.push(ValidationError::invalid_message(
+                    self.messages.len() - 1,
+                    "a final assistant message must not end with whitespace, see Messages::assistant_prefill",
+                ));
+            }
+        }
+    }
+    /// Check max_tokens against the maximum output tokens of the model
+    /// Models missing from the table are not checked
+    pub fn validate_model_limits(&self, limits: &ModelLimits) -> Result<(), ValidationError> {
+        if let Some(limit) = limits.max_output_tokens(&self.model) {
+            if self.max_tokens > limit {
+                return Err(ValidationError::invalid_value(
+                    "max_tokens",
+                    self.max_tokens,
+                    &format!("{} supports at most {} output tokens", self.model, limit),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Smallest thinking budget accepted by the API
+const MIN_THINKING_BUDGET_TOKENS: u32 = 1024;
+
+/// Configuration of extended thinking
+/// Enabled: The model thinks before answering, using up to budget_tokens of max_tokens
+/// Disabled: The model answers directly
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Thinking {
+    Enabled { budget_tokens: u32 },
+    Disabled,
+}
+
+/// Metadata about the request
+/// user_id: An external identifier for the user associated with the request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+}
+impl Metadata {
+    pub fn new(user_id: String) -> Self {
+        Self {
+            user_id: Some(user_id),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// System prompt sent with the request
+/// Text: A plain string system prompt
+/// Blocks: A list of text content blocks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SystemPrompt {
+    Text(String),
+    Blocks(Vec<ContentType>),
+}
+impl SystemPrompt {
+    /// Create a system prompt with a cached static preamble followed by an uncached suffix
+    /// preamble: The large prompt that stays the same between requests
+    /// suffix: The part that changes between requests, e.g. the current date
+    pub fn cached_preamble(preamble: impl Into<String>, suffix: impl Into<String>) -> Self {
+        Self::Blocks(vec![
+            ContentType::new_text_cached(preamble.into()),
+            ContentType::new_text(suffix.into()),
+        ])
+    }
+}
+impl From<String> for SystemPrompt {
+    fn from(system: String) -> Self {
+        Self::Text(system)
+    }
+}
+impl From<&str> for SystemPrompt {
+    fn from(system: &str) -> Self {
+        Self::Text(system.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum MessageContent {
     String(String),
@@ -222,18 +871,110 @@ impl MessageContent {
             .collect();
         Self::ContentArray(content)
     }
+    /// True if a block references a file uploaded with the Files API
+    pub fn references_file(&self) -> bool {
+        match self {
+            Self::String(_) => false,
+            Self::ContentArray(blocks) => blocks.iter().any(ContentType::references_file),
+        }
+    }
+    /// True when there is no content block, see [`MessageContent::len`]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Number of content blocks, a string counts as one text block unless it is empty
+    pub fn len(&self) -> usize {
+        match self {
+            Self::String(text) => usize::from(!text.is_empty()),
+            Self::ContentArray(blocks) => blocks.len(),
+        }
+    }
+    /// Append a block, a string is turned into a text block first unless it is empty
+    pub fn push(&mut self, block: ContentType) {
+        match self {
+            Self::String(text) => {
+                let mut blocks = vec![];
+                if !text.is_empty() {
+                    blocks.push(ContentType::new_text(std::mem::take(text)));
+                }
+                blocks.push(block);
+                *self = Self::ContentArray(blocks);
+            }
+            Self::ContentArray(blocks) => blocks.push(block),
+        }
+    }
+    /// Append a text block
+    pub fn push_text(&mut self, text: impl Into<String>) {
+        self.push(ContentType::new_text(text.into()));
+    }
+    /// Append an image block
+    pub fn push_image(&mut self, source: Source) {
+        self.push(ContentType::new_image(source));
+    }
+    /// The text of the content, text blocks are joined with a newline and other blocks ignored
+    /// Each block of a request is a separate text, unlike [`ResponseBodyAnthropic::text`] which joins without a separator
+    /// Empty when there is no text block, e.g. for an image only message
+    pub fn as_text(&self) -> String {
+        match self {
+            Self::String(text) => text.clone(),
+            Self::ContentArray(blocks) => blocks
+                .iter()
+                .filter_map(ContentType::text)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+/// Writes [`MessageContent::as_text`]
+impl fmt::Display for MessageContent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_text())
+    }
+}
+impl From<&str> for MessageContent {
+    fn from(content: &str) -> Self {
+        Self::String(content.to_string())
+    }
+}
+impl From<String> for MessageContent {
+    fn from(content: String) -> Self {
+        Self::String(content)
+    }
+}
+impl From<Vec<ContentType>> for MessageContent {
+    fn from(blocks: Vec<ContentType>) -> Self {
+        Self::ContentArray(blocks)
+    }
 }
 /// Messages to be sent to the API
 /// role: The role of the message
 /// content: The content of the message
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Messages {
     pub role: Role,
     pub content: MessageContent,
 }
 impl Messages {
-    pub fn new(role: Role, content: MessageContent) -> Self {
-        Self { role, content }
+    /// Create a new message
+    /// content: Anything convertible to [`MessageContent`], e.g. `&str` or `Vec<ContentType>`
+    pub fn new(role: Role, content: impl Into<MessageContent>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+        }
+    }
+    /// Start building a user message from text and image blocks
+    pub fn user() -> MessageBuilder {
+        MessageBuilder::new(Role::User)
+    }
+    /// Create a user message with any content blocks, e.g. text and images
+    /// See [`Messages::user`] to add the blocks one by one
+    pub fn user_with_content(blocks: Vec<ContentType>) -> Self {
+        Self::new(Role::User, blocks)
+    }
+    /// Start building an assistant message from content blocks
+    pub fn assistant() -> MessageBuilder {
+        MessageBuilder::new(Role::Assistant)
     }
     /// Create a new message prompt
     /// content: The content of the message
@@ -257,20 +998,66 @@ impl Messages {
             content: MessageContent::String(content),
         }
     }
+    /// Create an assistant message the response continues from, sent as the last message
+    /// e.g. `{` to get JSON back, the response does not repeat the prefill
+    /// Trailing whitespace is trimmed as the API rejects a final assistant message ending with it
+    pub fn assistant_prefill(content: String) -> Self {
+        Self::new_assistant_message_prompt(content.trim_end().to_string())
+    }
+    /// Create a new user message sending back the result of a tool call
+    /// tool_use_id: The id of the tool_use block this is the result of
+    /// content: The output of the tool, a string or text and image blocks
+    /// is_error: Set to true if the tool call failed
+    pub fn new_tool_result_message(
+        tool_use_id: String,
+        content: impl Into<MessageContent>,
+        is_error: Option<bool>,
+    ) -> Self {
+        Self {
+            role: Role::User,
+            content: MessageContent::ContentArray(vec![ContentType::new_tool_result(
+                tool_use_id,
+                content,
+                is_error,
+            )]),
+        }
+    }
+}
+
+/// A user message with text content
+impl From<&str> for Messages {
+    fn from(content: &str) -> Self {
+        Self::new_user_message_prompt(content.to_string())
+    }
+}
+/// A user message with text content
+impl From<String> for Messages {
+    fn from(content: String) -> Self {
+        Self::new_user_message_prompt(content)
+    }
+}
+/// A message with any content, e.g. `(Role::Assistant, "Hi")`
+impl<T: Into<MessageContent>> From<(Role, T)> for Messages {
+    fn from((role, content): (Role, T)) -> Self {
+        Self::new(role, content)
+    }
+}
+/// The assistant turn of a response, sent back unchanged to continue the conversation,
+/// e.g. before the tool results of its tool_use blocks
+impl From<ResponseBodyAnthropic> for Messages {
+    fn from(response: ResponseBodyAnthropic) -> Self {
+        Self::new(response.role, response.content)
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum Role {
     #[serde(rename = "user")]
+    #[default]
     User,
     #[serde(rename = "assistant")]
     Assistant,
 }
-impl Default for Role {
-    fn default() -> Self {
-        Self::User
-    }
-}
 impl Role {
     pub fn new(role: &str) -> Self {
         match role {
@@ -280,71 +1067,823 @@ impl Role {
         }
     }
 }
-#[derive(Debug, Serialize, Deserialize)]
-
+/// Message returned by the API
+/// container: The container used by server tools such as code execution
+/// extra: Fields added to the API after this version of the crate, kept as raw JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ResponseBodyAnthropic {
     pub id: String,
     pub model: String,
     pub role: Role,
-    pub stop_reason: String,
+    pub stop_reason: StopReason,
     pub stop_sequence: Option<String>,
     #[serde(rename = "type")]
     pub message_type: String,
     pub usage: Usage,
     pub content: Vec<ContentType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<Container>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+impl ResponseBodyAnthropic {
+    /// The text of all text blocks joined without a separator, other blocks are ignored
+    /// A response splits one answer into several blocks around citations, unlike [`MessageContent::as_text`]
+    pub fn text(&self) -> String {
+        self.content.iter().filter_map(ContentType::text).collect()
+    }
+    /// The reasoning of all thinking blocks joined together, empty without extended thinking
+    /// Redacted thinking is encrypted and not included
+    pub fn thinking(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentType::Thinking(thinking) => Some(thinking.thinking.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+    /// The text of the first text block, if any
+    pub fn first_text(&self) -> Option<&str> {
+        self.content.iter().find_map(ContentType::text)
+    }
+    /// Each cited text span of the response paired with one of its citations
+    /// A span citing several documents appears once per citation
+    pub fn citations(&self) -> Vec<(&str, &Citation)> {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentType::Text(text) => Some(text),
+                _ => None,
+            })
+            .flat_map(|text| {
+                text.citations
+                    .iter()
+                    .flatten()
+                    .map(|citation| (text.text.as_str(), citation))
+            })
+            .collect()
+    }
+    /// The urls of every page found by the web search tool, in the order of the results
+    pub fn searched_urls(&self) -> Vec<&str> {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentType::WebSearchToolResult(result) => Some(&result.content),
+                _ => None,
+            })
+            .flat_map(|content| match content {
+                WebSearchToolResultContent::Results(results) => results.as_slice(),
+                WebSearchToolResultContent::Error(_) => &[],
+            })
+            .map(|result| result.url.as_str())
+            .collect()
+    }
+    /// The calls of client tools the caller must execute, in the order the model made them
+    /// Calls of server tools are in [`server_tool_uses`](Self::server_tool_uses)
+    pub fn tool_uses(&self) -> Vec<&ContentToolUse> {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentType::ToolUse(tool_use) => Some(tool_use),
+                _ => None,
+            })
+            .collect()
+    }
+    /// The calls of tools already executed by the API, e.g. web searches
+    pub fn server_tool_uses(&self) -> Vec<&ContentServerToolUse> {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentType::ServerToolUse(server_tool_use) => Some(server_tool_use),
+                _ => None,
+            })
+            .collect()
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Content {
-    #[serde(rename = "type")]
-    pub content_type: String,
-    pub text: Option<String>,
-    pub data: Option<String>,
-    pub media_type: Option<MediaType>,
+/// The reason the model stopped generating
+/// EndTurn: The model reached a natural stopping point
+/// MaxTokens: The requested max_tokens was reached
+/// StopSequence: One of the custom stop sequences was generated
+/// ToolUse: The model invoked one or more tools
+/// PauseTurn: A long running server tool turn was paused, send the response back to continue it
+/// Refusal: The model declined to answer for safety reasons
+/// Unknown: A stop reason not known to this crate yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    EndTurn,
+    MaxTokens,
+    StopSequence,
+    ToolUse,
+    PauseTurn,
+    Refusal,
+    Unknown(String),
+}
+impl StopReason {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::EndTurn => "end_turn",
+            Self::MaxTokens => "max_tokens",
+            Self::StopSequence => "stop_sequence",
+            Self::ToolUse => "tool_use",
+            Self::PauseTurn => "pause_turn",
+            Self::Refusal => "refusal",
+            Self::Unknown(reason) => reason,
+        }
+    }
+    /// Returns true if the model finished its turn, either naturally or on a stop sequence
+    pub fn is_complete(&self) -> bool {
+        matches!(self, Self::EndTurn | Self::StopSequence)
+    }
+    /// Returns true if the model is waiting for the results of its tool calls
+    pub fn is_tool_use(&self) -> bool {
+        matches!(self, Self::ToolUse)
+    }
+    /// Returns true if the response was cut off by max_tokens
+    pub fn is_max_tokens(&self) -> bool {
+        matches!(self, Self::MaxTokens)
+    }
+}
+impl From<&str> for StopReason {
+    fn from(reason: &str) -> Self {
+        match reason {
+            "end_turn" => Self::EndTurn,
+            "max_tokens" => Self::MaxTokens,
+            "stop_sequence" => Self::StopSequence,
+            "tool_use" => Self::ToolUse,
+            "pause_turn" => Self::PauseTurn,
+            "refusal" => Self::Refusal,
+            _ => Self::Unknown(reason.to_string()),
+        }
+    }
+}
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+impl Serialize for StopReason {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+impl<'de> Deserialize<'de> for StopReason {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let reason = String::deserialize(deserializer)?;
+        Ok(Self::from(reason.as_str()))
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Container of a response, reused by passing its id with the next request
+/// expires_at: RFC 3339 timestamp after which the container is deleted
+/// extra: Fields added to the API after this version of the crate, kept as raw JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Container {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
 
+/// Token usage of a message
+/// server_tool_use: Requests made by server tools, billed separately
+/// extra: Fields added to the API after this version of the crate, kept as raw JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct Usage {
     pub input_tokens: i32,
     pub output_tokens: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<UsageServiceTier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_tokens: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_tool_use: Option<ServerToolUsage>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ContentText {
-    pub text: String,
-    #[serde(rename = "type")]
-    pub content_type: String,
+impl Usage {
+    /// Total tokens of the request: input + output + tokens read from the prompt cache
+    pub fn total_tokens(&self) -> i32 {
+        self.input_tokens + self.output_tokens + self.cache_read_input_tokens.unwrap_or(0)
+    }
 }
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ContentImage {
-    pub source: Source,
-    #[serde(rename = "type")]
-    pub content_type: String,
+
+/// Requests made by server tools during a message
+/// extra: Fields added to the API after this version of the crate, kept as raw JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerToolUsage {
+    #[serde(default)]
+    pub web_search_requests: u32,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Debug, Serialize, Deserialize)]
 
-pub struct Source {
-    #[serde(rename = "type")]
-    pub content_type: String,
-    pub data: String,
-    pub media_type: MediaType,
+/// Service tier requested for a message, controls whether priority capacity is used
+/// Auto: Priority capacity is used when available
+/// StandardOnly: Only standard capacity is used
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceTier {
+    Auto,
+    StandardOnly,
 }
-impl Source {
-    /// Create a new source
-    /// content_type: The content type of the source
-    /// data : Image Base65 data
-    /// media_type: The media type of the source
-    pub fn new(data: String, media_type: MediaType) -> Self {
-        Self {
-            content_type: "base64".to_string(),
-            data,
-            media_type,
-        }
-    }
+/// Service tier a message was processed with, reported in its [`Usage`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageServiceTier {
+    Standard,
+    Priority,
+    Batch,
 }
-#[derive(Debug, Serialize, Deserialize)]
-pub enum MediaType {
-    #[serde(rename = "image/jpeg")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentText {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+    /// Sources of the text, set on responses to documents with citations enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub citations: Option<Vec<Citation>>,
+}
+/// Part of a document cited by a text block of the response
+/// The location type depends on the source of the cited document
+/// CharLocation: Character range of a text document, the end index is exclusive
+/// PageLocation: Page range of a PDF, pages start at 1 and the end page is exclusive
+/// ContentBlockLocation: Block range of a content document, the end index is exclusive
+/// WebSearchResultLocation: A page found by the web search tool
+/// SearchResultLocation: Block range of a search result block, the end index is exclusive
+/// Unknown: A location type added to the API after this version of the crate, kept as raw JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Citation {
+    CharLocation {
+        cited_text: String,
+        document_index: usize,
+        document_title: Option<String>,
+        start_char_index: usize,
+        end_char_index: usize,
+    },
+    PageLocation {
+        cited_text: String,
+        document_index: usize,
+        document_title: Option<String>,
+        start_page_number: usize,
+        end_page_number: usize,
+    },
+    ContentBlockLocation {
+        cited_text: String,
+        document_index: usize,
+        document_title: Option<String>,
+        start_block_index: usize,
+        end_block_index: usize,
+    },
+    WebSearchResultLocation {
+        cited_text: String,
+        url: String,
+        title: Option<String>,
+        encrypted_index: String,
+    },
+    SearchResultLocation {
+        cited_text: String,
+        source: String,
+        title: Option<String>,
+        search_result_index: usize,
+        start_block_index: usize,
+        end_block_index: usize,
+    },
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+impl Citation {
+    /// The text of the document being cited
+    pub fn cited_text(&self) -> &str {
+        match self {
+            Self::CharLocation { cited_text, .. }
+            | Self::PageLocation { cited_text, .. }
+            | Self::ContentBlockLocation { cited_text, .. }
+            | Self::WebSearchResultLocation { cited_text, .. }
+            | Self::SearchResultLocation { cited_text, .. } => cited_text,
+            Self::Unknown(value) => value["cited_text"].as_str().unwrap_or_default(),
+        }
+    }
+    /// Index of the cited document among the documents of the request, `None` for web and search results
+    pub fn document_index(&self) -> Option<usize> {
+        match self {
+            Self::CharLocation { document_index, .. }
+            | Self::PageLocation { document_index, .. }
+            | Self::ContentBlockLocation { document_index, .. } => Some(*document_index),
+            Self::WebSearchResultLocation { .. }
+            | Self::SearchResultLocation { .. }
+            | Self::Unknown(_) => None,
+        }
+    }
+    /// Title of the cited document, web page or search result, if it has one
+    pub fn document_title(&self) -> Option<&str> {
+        match self {
+            Self::CharLocation { document_title, .. }
+            | Self::PageLocation { document_title, .. }
+            | Self::ContentBlockLocation { document_title, .. } => document_title.as_deref(),
+            Self::WebSearchResultLocation { title, .. }
+            | Self::SearchResultLocation { title, .. } => title.as_deref(),
+            Self::Unknown(value) => value["title"].as_str(),
+        }
+    }
+}
+/// Result of a search, passed to the model to answer with citations
+/// source: Where the result comes from, usually a url
+/// title: The title of the result
+/// content: The text blocks of the result
+/// citations: Whether the model cites the result in its answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentSearchResult {
+    pub source: String,
+    pub title: String,
+    pub content: Vec<ContentType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub citations: Option<CitationsConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+/// Results of the server side web search tool
+/// tool_use_id: The id of the `server_tool_use` block that ran the search
+/// content: The pages found, or the error of the search
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentWebSearchToolResult {
+    pub tool_use_id: String,
+    pub content: WebSearchToolResultContent,
+}
+/// Content of a web search tool result, the search either found pages or failed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum WebSearchToolResultContent {
+    Results(Vec<WebSearchResult>),
+    Error(WebSearchToolResultError),
+}
+/// Page found by the web search tool
+/// encrypted_content: The content of the page, must be sent back unchanged in later turns
+/// page_age: When the page was last updated, if known
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebSearchResult {
+    pub url: String,
+    pub title: String,
+    pub encrypted_content: String,
+    pub page_age: Option<String>,
+    #[serde(rename = "type")]
+    pub result_type: String,
+}
+/// Error of a failed web search
+/// error_code: e.g. `max_uses_exceeded`, `too_many_requests` or `query_too_long`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebSearchToolResultError {
+    pub error_code: String,
+    #[serde(rename = "type")]
+    pub error_type: String,
+}
+/// Enables citations on a document or search result block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CitationsConfig {
+    pub enabled: bool,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentImage {
+    pub source: Source,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+/// Document the model can read, e.g. a PDF
+/// source: The content of the document
+/// title: Title of the document, returned with citations
+/// context: Context about the document, read by the model but never cited
+/// citations: Whether the model cites the document in its answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentDocument {
+    pub source: DocumentSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub citations: Option<CitationsConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+/// Source of a document block
+/// Base64: A base64 encoded file, e.g. a PDF
+/// Text: Plain text, `media_type` is always [`DocumentMediaType::PlainText`]
+/// Content: Pre-chunked content, each block can be cited on its own
+/// Url: An http(s) url the API downloads the document from
+/// File: A document uploaded with the Files API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DocumentSource {
+    Base64 {
+        media_type: DocumentMediaType,
+        data: String,
+    },
+    Text {
+        media_type: DocumentMediaType,
+        data: String,
+    },
+    Content {
+        content: Vec<ContentType>,
+    },
+    Url {
+        url: String,
+    },
+    File {
+        file_id: String,
+    },
+}
+impl DocumentSource {
+    /// Create a new base64 PDF source
+    /// data: The base64 encoded PDF
+    pub fn new_pdf(data: String) -> Self {
+        Self::Base64 {
+            media_type: DocumentMediaType::Pdf,
+            data,
+        }
+    }
+    /// Create a new plain text source
+    pub fn new_text(data: String) -> Self {
+        Self::Text {
+            media_type: DocumentMediaType::PlainText,
+            data,
+        }
+    }
+    /// Create a new source from content blocks, usually text blocks
+    pub fn new_content(content: Vec<ContentType>) -> Self {
+        Self::Content { content }
+    }
+    /// Create a new source referencing a document uploaded with the Files API
+    /// file_id: The id of the uploaded file
+    pub fn new_file(file_id: String) -> Self {
+        Self::File { file_id }
+    }
+    /// Create a new source referencing a document by url
+    /// Fails if the url is not a valid http or https url
+    pub fn new_url(url: String) -> Result<Self, ValidationError> {
+        match reqwest::Url::parse(&url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(Self::Url { url }),
+            _ => Err(ValidationError::invalid_value(
+                "source.url",
+                url,
+                "must be an http or https url",
+            )),
+        }
+    }
+}
+/// Media type of a document source, images use [`MediaType`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DocumentMediaType {
+    #[serde(rename = "application/pdf")]
+    Pdf,
+    #[serde(rename = "text/plain")]
+    PlainText,
+}
+/// Marks a content block as a prompt caching breakpoint
+/// Everything up to and including the block is cached
+/// cache_type: Only "ephemeral" is supported by the API
+/// ttl: How long the cache entry lives, the API defaults to 5 minutes when omitted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheControl {
+    #[serde(rename = "type")]
+    pub cache_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<CacheTtl>,
+}
+impl CacheControl {
+    pub fn ephemeral() -> Self {
+        Self {
+            cache_type: "ephemeral".to_string(),
+            ttl: None,
+        }
+    }
+    /// Create an ephemeral cache breakpoint with an explicit lifetime
+    pub fn ephemeral_with_ttl(ttl: CacheTtl) -> Self {
+        Self {
+            ttl: Some(ttl),
+            ..Self::ephemeral()
+        }
+    }
+}
+/// Lifetime of a cache entry, the only values accepted by the API
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CacheTtl {
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+}
+/// Reasoning of the model when extended thinking is enabled
+/// thinking: The reasoning text
+/// signature: Must be sent back unchanged when the block is included in a later request, streamed separately as a signature_delta
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentThinking {
+    pub thinking: String,
+    #[serde(default)]
+    pub signature: String,
+}
+/// Reasoning flagged by the safety systems, sent encrypted
+/// data: The encrypted reasoning, must be sent back unchanged like the signature of a thinking block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentRedactedThinking {
+    pub data: String,
+}
+/// Tool call made by the model
+/// id: Identifier of the tool call, referenced by the matching tool result
+/// name: The name of the tool being called
+/// input: The input to the tool, following its input_schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentToolUse {
+    pub id: String,
+    pub name: String,
+    pub input: serde_json::Value,
+}
+/// Call of a tool executed by the API, e.g. web search or code execution
+/// The result follows in the same response, the caller must not execute it
+/// id: Identifier of the call, referenced by the matching result block
+/// name: The name of the server tool, e.g. `web_search`
+/// input: The input of the call, e.g. the search query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentServerToolUse {
+    pub id: String,
+    pub name: String,
+    pub input: serde_json::Value,
+}
+/// Result of a tool call sent back to the model
+/// tool_use_id: The id of the tool_use block this is the result of
+/// content: The output of the tool, as a string or content blocks
+/// is_error: Set to true if the tool call failed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentToolResult {
+    pub tool_use_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<MessageContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_error: Option<bool>,
+}
+/// Source of an image block
+/// Base64: The image sent inline as base64 data with its media type
+/// Url: Url the API downloads the image from
+/// File: An image uploaded with the Files API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Source {
+    Base64 { media_type: MediaType, data: String },
+    Url { url: String },
+    File { file_id: String },
+}
+impl Source {
+    /// Check an inline image against the limits of the API before uploading it
+    /// The data must be valid base64 without a data uri prefix
+    /// The decoded size must be at most [`DEFAULT_MAX_IMAGE_BYTES`]
+    /// With the `image` feature the width and height must be at most [`MAX_IMAGE_DIMENSION`],
+    /// images whose header can't be read are left for the API to reject
+    /// Url and file sources are not checked
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let Self::Base64 { data, .. } = self else {
+            return Ok(());
+        };
+        if data.is_empty() {
+            return Err(ValidationError::invalid_value(
+                "source.data",
+                "\"\"",
+                "must not be empty",
+            ));
+        }
+        let padding = data.bytes().rev().take_while(|byte| *byte == b'=').count();
+        let size = (data.len() / 4 * 3).saturating_sub(padding);
+        if size > DEFAULT_MAX_IMAGE_BYTES {
+            return Err(ValidationError::invalid_value(
+                "source.data",
+                format!("{} bytes", size),
+                &format!("exceeds the limit of {} bytes", DEFAULT_MAX_IMAGE_BYTES),
+            ));
+        }
+        #[cfg_attr(not(feature = "image"), allow(unused_variables))]
+        let bytes = STANDARD.decode(data).map_err(|_| {
+            let reason = if data.starts_with("data:") {
+                "is a data uri, create the source with Source::from_data_uri"
+            } else {
+                "is not valid base64, create the source from raw bytes with Source::from_bytes"
+            };
+            ValidationError::invalid_value("source.data", format!("{} bytes", size), reason)
+        })?;
+        #[cfg(feature = "image")]
+        {
+            let dimensions = image::ImageReader::new(std::io::Cursor::new(bytes))
+                .with_guessed_format()
+                .ok()
+                .and_then(|reader| reader.into_dimensions().ok());
+            if let Some((width, height)) = dimensions {
+                if width > MAX_IMAGE_DIMENSION || height > MAX_IMAGE_DIMENSION {
+                    return Err(ValidationError::invalid_value(
+                        "source.data",
+                        format!("{}x{} pixels", width, height),
+                        &format!(
+                            "exceeds the limit of {} pixels per side",
+                            MAX_IMAGE_DIMENSION
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Create a new source
+    /// data : Image Base64 data
+    /// media_type: The media type of the source
+    pub fn new(data: String, media_type: MediaType) -> Self {
+        Self::Base64 { media_type, data }
+    }
+    /// Create a new source from a base64 data uri such as `data:image/png;base64,iVBORw0...`
+    /// Fails if the uri is not a base64 data uri, the media type is not an image type
+    /// supported by the API or the data is not valid base64
+    pub fn from_data_uri(uri: &str) -> Result<Self, ValidationError> {
+        let (media_type, data) = uri
+            .strip_prefix("data:")
+            .and_then(|uri| uri.split_once(','))
+            .and_then(|(header, data)| Some((header.strip_suffix(";base64")?, data)))
+            .ok_or_else(|| {
+                let prefix: String = uri.chars().take(32).collect();
+                ValidationError::invalid_value(
+                    "source",
+                    format!("{:?}", prefix),
+                    "must be a base64 data uri such as data:image/png;base64,...",
+                )
+            })?;
+        let media_type = MediaType::from_mime(media_type).ok_or_else(|| {
+            ValidationError::invalid_value(
+                "source.media_type",
+                media_type,
+                "must be image/jpeg, image/png, image/gif or image/webp",
+            )
+        })?;
+        let source = Self::new(data.to_string(), media_type);
+        source.validate()?;
+        Ok(source)
+    }
+    /// Create a new source referencing an image by url
+    /// url: The url of the image, downloaded by the API
+    pub fn new_url(url: String) -> Self {
+        Self::Url { url }
+    }
+    /// Create a new source referencing an image uploaded with the Files API
+    /// file_id: The id of the uploaded file, e.g. `file_011CNha8iCJcU1wXNR6q4V8w`
+    pub fn new_file(file_id: String) -> Self {
+        Self::File { file_id }
+    }
+    /// Create a new source from a local image file, use [`Source::new_file`] for the Files API
+    /// The media type is detected from the first bytes of the file, falling back to the extension
+    /// Fails if the file cannot be read or is not a jpeg, png, gif or webp image
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let path = path.as_ref();
+        Self::from_file_data(path, std::fs::read(path)?)
+    }
+    /// Same as `from_file` reading the file without blocking the runtime
+    pub async fn from_file_async(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let path = path.as_ref();
+        Self::from_file_data(path, tokio::fs::read(path).await?)
+    }
+    /// Same as [`Source::from_file`]
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        Self::from_file(path)
+    }
+    /// Same as [`Source::from_file_async`]
+    pub async fn from_path_async(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        Self::from_file_async(path).await
+    }
+    fn from_file_data(path: &Path, data: Vec<u8>) -> Result<Self, anyhow::Error> {
+        let media_type = MediaType::from_bytes(&data)
+            .or_else(|| MediaType::from_path(path))
+            .ok_or_else(|| anyhow::anyhow!("Unsupported image format: {}", path.display()))?;
+        Ok(Self::from_bytes(&data, media_type)?)
+    }
+    /// Download an image and create a base64 source from it
+    /// The media type is read from the `content-type` header, falling back to the extension of
+    /// the url when the header is missing or `application/octet-stream`
+    /// client: The client used for the download, a new one is created when `None`
+    /// max_bytes: Largest accepted image, see [`DEFAULT_MAX_IMAGE_BYTES`]
+    /// Use [`Source::new_url`] instead to let the API download a public image
+    pub async fn from_url(
+        url: &str,
+        client: Option<&reqwest::Client>,
+        max_bytes: usize,
+    ) -> Result<Self, ImageDownloadError> {
+        let mut res = match client {
+            Some(client) => client.get(url).send().await?,
+            None => reqwest::get(url).await?,
+        };
+        if !res.status().is_success() {
+            return Err(ImageDownloadError::Status(res.status().as_u16()));
+        }
+        let content_type = res
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| {
+                value
+                    .split(';')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string()
+            });
+        let media_type = match content_type.as_deref() {
+            Some(mime) if MediaType::from_mime(mime).is_some() => MediaType::from_mime(mime),
+            None | Some("application/octet-stream") => res
+                .url()
+                .path()
+                .rsplit_once('.')
+                .and_then(|(_, extension)| MediaType::from_extension(extension)),
+            Some(_) => None,
+        }
+        .ok_or_else(|| {
+            ImageDownloadError::UnsupportedContentType(
+                content_type.unwrap_or_else(|| "unknown".to_string()),
+            )
+        })?;
+        if let Some(size) = res.content_length() {
+            if size as usize > max_bytes {
+                return Err(ImageDownloadError::TooLarge {
+                    size: size as usize,
+                    max_bytes,
+                });
+            }
+        }
+        // The content length can be missing or wrong, the limit is enforced while reading
+        let mut data = vec![];
+        while let Some(chunk) = res.chunk().await? {
+            data.extend_from_slice(&chunk);
+            if data.len() > max_bytes {
+                return Err(ImageDownloadError::TooLarge {
+                    size: data.len(),
+                    max_bytes,
+                });
+            }
+        }
+        Ok(Self::new(STANDARD.encode(data), media_type))
+    }
+    /// Create a new source from raw bytes, base64 encoded with the standard alphabet
+    /// Fails if `bytes` is empty
+    pub fn from_bytes(bytes: &[u8], media_type: MediaType) -> Result<Self, ValidationError> {
+        if bytes.is_empty() {
+            return Err(ValidationError::invalid_value(
+                "source.data",
+                "[]",
+                "must not be empty",
+            ));
+        }
+        Ok(Self::new(STANDARD.encode(bytes), media_type))
+    }
+    /// Create a new source from raw bytes, downscaled so its longest edge is at most `max_edge`
+    /// The aspect ratio is preserved, JPEG images are encoded as JPEG and the others as PNG
+    /// Images already within bounds are kept as is
+    /// Returns the source with the final width and height, see [`RECOMMENDED_MAX_IMAGE_EDGE`]
+    /// Fails if the image can't be decoded or `max_edge` is 0
+    #[cfg(feature = "image-resize")]
+    pub fn from_bytes_resized(
+        bytes: &[u8],
+        media_type: MediaType,
+        max_edge: u32,
+    ) -> Result<(Self, (u32, u32)), anyhow::Error> {
+        use image::{imageops::FilterType, DynamicImage, ImageFormat};
+
+        if max_edge == 0 {
+            anyhow::bail!("max_edge must be greater than 0");
+        }
+        let image = image::load_from_memory(bytes)?;
+        if image.width().max(image.height()) <= max_edge {
+            let dimensions = (image.width(), image.height());
+            return Ok((Self::from_bytes(bytes, media_type)?, dimensions));
+        }
+        let image = image.resize(max_edge, max_edge, FilterType::Lanczos3);
+        let mut encoded = std::io::Cursor::new(vec![]);
+        let media_type = match media_type {
+            MediaType::Jpeg => {
+                // JPEG has no alpha channel
+                DynamicImage::ImageRgb8(image.to_rgb8())
+                    .write_to(&mut encoded, ImageFormat::Jpeg)?;
+                MediaType::Jpeg
+            }
+            _ => {
+                image.write_to(&mut encoded, ImageFormat::Png)?;
+                MediaType::Png
+            }
+        };
+        Ok((
+            Self::from_bytes(encoded.get_ref(), media_type)?,
+            (image.width(), image.height()),
+        ))
+    }
+}
+/// Media type of an image source, documents use [`DocumentMediaType`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MediaType {
+    #[serde(rename = "image/jpeg")]
     Jpeg,
     #[serde(rename = "image/png")]
     Png,
@@ -353,34 +1892,310 @@ pub enum MediaType {
     #[serde(rename = "image/webp")]
     Webp,
 }
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(untagged)]
-
+impl MediaType {
+    /// Image media type of a MIME type such as `image/png`, case insensitive
+    /// Parameters are ignored, so a `content-type` header like `image/png; q=0.9` works
+    /// Inverse of [`MediaType::to_mime`], `None` for any MIME type that is not a supported image
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        let mime = mime.split(';').next().unwrap_or_default().trim();
+        match mime.to_ascii_lowercase().as_str() {
+            "image/jpeg" | "image/jpg" => Some(Self::Jpeg),
+            "image/png" => Some(Self::Png),
+            "image/gif" => Some(Self::Gif),
+            "image/webp" => Some(Self::Webp),
+            _ => None,
+        }
+    }
+    /// The MIME type sent to the API, e.g. `image/png`
+    pub fn to_mime(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Gif => "image/gif",
+            Self::Webp => "image/webp",
+        }
+    }
+    /// Image media type of a file extension such as `png` or `.JPG`, case insensitive
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension
+            .trim_start_matches('.')
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            "gif" => Some(Self::Gif),
+            "webp" => Some(Self::Webp),
+            _ => None,
+        }
+    }
+    /// Image media type of a file path from its extension, the file is not read
+    /// Use [`MediaType::from_bytes`] on the content when the extension can't be trusted
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        path.as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Self::from_extension)
+    }
+    /// Image media type sniffed from the magic number at the start of the data
+    /// WebP is only detected when the RIFF container holds a `WEBP` form type
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        match data {
+            [0xFF, 0xD8, 0xFF, ..] => Some(Self::Jpeg),
+            [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n', ..] => Some(Self::Png),
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some(Self::Gif),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some(Self::Webp),
+            _ => None,
+        }
+    }
+}
+/// Content block of a message, tagged with its `type` on the wire
+///
+/// Migration: the blocks no longer have a `content_type` field, the type is owned by the
+/// variant. Remove `content_type: "text".to_string()` and the like from struct literals,
+/// or use the constructors such as [`ContentType::new_text`], and match on the variant
+/// instead of reading the field.
+///
+/// The `Content` struct was removed, match on the variant instead: its `text` is
+/// [`ContentType::text`], its `data` and `media_type` are the [`Source`] of [`ContentImage`].
+///
+/// Unknown: A block type added to the API after this version of the crate, kept as raw JSON
+/// and sent back unchanged. A malformed block of a known type is still an error.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum ContentType {
-    #[serde(rename = "text")]
     Text(ContentText),
-    #[serde(rename = "image")]
     Image(ContentImage),
+    ToolUse(ContentToolUse),
+    ServerToolUse(ContentServerToolUse),
+    ToolResult(ContentToolResult),
+    Thinking(ContentThinking),
+    RedactedThinking(ContentRedactedThinking),
+    Document(ContentDocument),
+    SearchResult(ContentSearchResult),
+    WebSearchToolResult(ContentWebSearchToolResult),
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+impl<'de> Deserialize<'de> for ContentType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        /// The known blocks, `Other` catches any other type
+        #[derive(Deserialize)]
+        #[serde(tag = "type", rename_all = "snake_case")]
+        enum Known {
+            Text(ContentText),
+            Image(ContentImage),
+            ToolUse(ContentToolUse),
+            ServerToolUse(ContentServerToolUse),
+            ToolResult(ContentToolResult),
+            Thinking(ContentThinking),
+            RedactedThinking(ContentRedactedThinking),
+            Document(ContentDocument),
+            SearchResult(ContentSearchResult),
+            WebSearchToolResult(ContentWebSearchToolResult),
+            #[serde(other)]
+            Other,
+        }
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let block = Known::deserialize(&value).map_err(serde::de::Error::custom)?;
+        Ok(match block {
+            Known::Text(block) => Self::Text(block),
+            Known::Image(block) => Self::Image(block),
+            Known::ToolUse(block) => Self::ToolUse(block),
+            Known::ServerToolUse(block) => Self::ServerToolUse(block),
+            Known::ToolResult(block) => Self::ToolResult(block),
+            Known::Thinking(block) => Self::Thinking(block),
+            Known::RedactedThinking(block) => Self::RedactedThinking(block),
+            Known::Document(block) => Self::Document(block),
+            Known::SearchResult(block) => Self::SearchResult(block),
+            Known::WebSearchToolResult(block) => Self::WebSearchToolResult(block),
+            Known::Other => Self::Unknown(value),
+        })
+    }
 }
 impl Default for ContentType {
     fn default() -> Self {
         Self::Text(ContentText {
             text: "".to_string(),
-            content_type: "".to_string(),
+            cache_control: None,
+            citations: None,
         })
     }
 }
 impl ContentType {
+    /// Sources of the images of the block, including the images of a tool result
+    fn image_sources(&self) -> Vec<&Source> {
+        match self {
+            Self::Image(image) => vec![&image.source],
+            Self::ToolResult(ContentToolResult {
+                content: Some(MessageContent::ContentArray(blocks)),
+                ..
+            }) => blocks.iter().flat_map(ContentType::image_sources).collect(),
+            _ => vec![],
+        }
+    }
+    /// True if the block, or a block nested in it, has a file source
+    pub fn references_file(&self) -> bool {
+        match self {
+            Self::Image(image) => matches!(image.source, Source::File { .. }),
+            Self::Document(document) => match &document.source {
+                DocumentSource::File { .. } => true,
+                DocumentSource::Content { content } => {
+                    content.iter().any(ContentType::references_file)
+                }
+                _ => false,
+            },
+            Self::ToolResult(result) => result
+                .content
+                .as_ref()
+                .is_some_and(MessageContent::references_file),
+            _ => false,
+        }
+    }
+    /// The text of a text block, `None` for other blocks
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(&text.text),
+            _ => None,
+        }
+    }
     pub fn new_text(text: String) -> Self {
         Self::Text(ContentText {
             text,
-            content_type: "text".to_string(),
+            cache_control: None,
+            citations: None,
+        })
+    }
+    /// Create a new text block marked for prompt caching
+    /// text: The text of the block, usually a large static prompt
+    pub fn new_text_cached(text: String) -> Self {
+        Self::Text(ContentText {
+            text,
+            cache_control: Some(CacheControl::ephemeral()),
+            citations: None,
+        })
+    }
+    /// Create a new text block cached for the given lifetime
+    /// text: The text of the block, usually a large static prompt
+    /// ttl: How long the cache entry lives
+    pub fn new_text_cached_with_ttl(text: String, ttl: CacheTtl) -> Self {
+        Self::Text(ContentText {
+            text,
+            cache_control: Some(CacheControl::ephemeral_with_ttl(ttl)),
+            citations: None,
         })
     }
     pub fn new_image(source: Source) -> Self {
         Self::Image(ContentImage {
             source,
-            content_type: "image".to_string(),
+            cache_control: None,
+        })
+    }
+    /// Create a new image block from an image file, see [`Source::from_file`]
+    pub fn new_image_from_file(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        Ok(Self::new_image(Source::from_file(path)?))
+    }
+    /// Create a new image block from raw bytes, see [`Source::from_bytes`]
+    pub fn new_image_from_bytes(
+        bytes: &[u8],
+        media_type: MediaType,
+    ) -> Result<Self, ValidationError> {
+        Ok(Self::new_image(Source::from_bytes(bytes, media_type)?))
+    }
+    /// Create a new PDF document block
+    /// data: The base64 encoded PDF
+    pub fn new_pdf(data: String) -> Self {
+        Self::new_document(DocumentSource::new_pdf(data), None, None)
+    }
+    /// Create a new document block referencing a document by url, downloaded by the API
+    /// Fails if the url is not a valid http or https url
+    pub fn new_document_url(url: String) -> Result<Self, ValidationError> {
+        Ok(Self::new_document(
+            DocumentSource::new_url(url)?,
+            None,
+            None,
+        ))
+    }
+    /// Create a new document block
+    /// source: The content of the document
+    /// title: Title of the document, returned with citations
+    /// context: Context about the document, never cited
+    pub fn new_document(
+        source: DocumentSource,
+        title: Option<String>,
+        context: Option<String>,
+    ) -> Self {
+        Self::Document(ContentDocument {
+            source,
+            title,
+            context,
+            citations: None,
+            cache_control: None,
+        })
+    }
+    /// Create a new search result block
+    /// source: Where the result comes from, usually a url
+    /// title: The title of the result
+    /// content: The text of the result, one text block per entry
+    pub fn new_search_result(source: String, title: String, content: Vec<String>) -> Self {
+        Self::SearchResult(ContentSearchResult {
+            source,
+            title,
+            content: content.into_iter().map(ContentType::new_text).collect(),
+            citations: None,
+            cache_control: None,
+        })
+    }
+    /// Enable citations, only applies to document and search result blocks
+    /// The text blocks of the response then carry the cited parts of the block
+    pub fn with_citations(mut self) -> Self {
+        let citations = Some(CitationsConfig { enabled: true });
+        match &mut self {
+            Self::Document(document) => document.citations = citations,
+            Self::SearchResult(search_result) => search_result.citations = citations,
+            _ => {}
+        }
+        self
+    }
+    /// Create a new PDF document block from a file
+    /// Fails if the file cannot be read or does not start with the `%PDF-` header
+    pub fn from_pdf_file(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        if !data.starts_with(b"%PDF-") {
+            return Err(anyhow::anyhow!("Not a PDF file: {}", path.display()));
+        }
+        Ok(Self::new_pdf(STANDARD.encode(data)))
+    }
+    /// Create a new image block referencing an image by url
+    /// url: The url of the image, downloaded by the API
+    pub fn new_image_url(url: String) -> Self {
+        Self::new_image(Source::new_url(url))
+    }
+    /// Create a new tool use block
+    /// id: Identifier of the tool call
+    /// name: The name of the tool
+    /// input: The input to the tool
+    pub fn new_tool_use(id: String, name: String, input: serde_json::Value) -> Self {
+        Self::ToolUse(ContentToolUse { id, name, input })
+    }
+    /// Create a new tool result block
+    /// tool_use_id: The id of the tool_use block this is the result of
+    /// content: The output of the tool
+    /// is_error: Set to true if the tool call failed
+    pub fn new_tool_result(
+        tool_use_id: String,
+        content: impl Into<MessageContent>,
+        is_error: Option<bool>,
+    ) -> Self {
+        Self::ToolResult(ContentToolResult {
+            tool_use_id,
+            content: Some(content.into()),
+            is_error,
         })
     }
 }
@@ -388,7 +2203,6 @@ impl ContentType {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use base64::{engine::general_purpose::STANDARD, Engine};
 
     #[tokio::test]
     async fn test_get_message_completed() {
@@ -403,15 +2217,68 @@ mod tests {
             max_tokens: 1000,
             messages,
             temperature: Some(0.1),
+            ..Default::default()
         };
-        match client.get_message_completed(body).await {
+        match client.get_message_completed(&body).await {
             Ok(res) => {
                 // assert_eq!(res.role, Role::Assistant);
                 println!("{:#?}", res);
             }
             Err(e) => {
-                println!("{:?}", e);
-                assert!(false);
+                panic!("{:?}", e);
+            }
+        }
+    }
+    #[tokio::test]
+    async fn test_system_prompt_message() {
+        dotenvy::dotenv().ok();
+        let client = AnthropicClient::default().unwrap();
+        let messages = vec![Messages::new_user_message_prompt(
+            "What is the capital of France?".to_string(),
+        )];
+        let body = RequestBodyAnthropic::new_with_system(
+            "claude-3-5-sonnet-20241022".to_string(),
+            1000,
+            messages,
+            Some(0.1),
+            "Always answer in uppercase letters only.".to_string(),
+        );
+        match client.get_message_completed(&body).await {
+            Ok(res) => {
+                println!("{:#?}", res);
+                for content in res.content {
+                    if let ContentType::Text(text) = content {
+                        assert_eq!(text.text, text.text.to_uppercase());
+                    }
+                }
+            }
+            Err(e) => {
+                panic!("{:?}", e);
+            }
+        }
+    }
+    #[tokio::test]
+    async fn test_stop_sequences_message() {
+        dotenvy::dotenv().ok();
+        let client = AnthropicClient::default().unwrap();
+        let messages = vec![Messages::new_user_message_prompt(
+            "Count from 1 to 10, separated by commas.".to_string(),
+        )];
+        let body = RequestBodyAnthropic::new(
+            "claude-3-5-sonnet-20241022".to_string(),
+            1000,
+            messages,
+            Some(0.1),
+        )
+        .with_stop_sequences(vec!["5".to_string()]);
+        match client.get_message_completed(&body).await {
+            Ok(res) => {
+                println!("{:#?}", res);
+                assert_eq!(res.stop_reason, StopReason::StopSequence);
+                assert_eq!(res.stop_sequence, Some("5".to_string()));
+            }
+            Err(e) => {
+                panic!("{:?}", e);
             }
         }
     }
@@ -428,15 +2295,15 @@ mod tests {
             max_tokens: 1000,
             messages,
             temperature: Some(0.1),
+            ..Default::default()
         };
-        match client.get_message_completed(body).await {
+        match client.get_message_completed(&body).await {
             Ok(res) => {
                 // assert_eq!(res.role, Role::Assistant);
                 println!("{:#?}", res);
             }
             Err(e) => {
-                println!("{:?}", e);
-                assert!(false);
+                panic!("{:?}", e);
             }
         }
     }
@@ -514,19 +2381,17 @@ Example 2:
 - The students solution doesnt need to match exactly with the provided system_solution, often it has different intermediate calculations. as long as the final result is mathematically the same consider the calculation as correct. note, these terms are equivalent "4-1" and "-1+4"
 - Pay particular attention to the subjectivity in geometric interpretations if the instructions leave some room for creative construction.
 - Ensure precision and clarity to avoid any misunderstanding, particularly in error explanations."#.to_string(),
-                content_type: "text".to_string(),
+                cache_control: None,
+                citations: None,
             }),
             ContentType::Text(ContentText {
                 text: r#"Assignment: Bestimme die Ableitung <math>f^\\prime(x)</math> für <math>f(x)=\\frac{1}{x^5}</math> mit der Potenzregel für Ableitungen.\n    /n System Solution: <p><strong>(Schritt 1) Berechnen der Ableitung &lt;math&gt;f^\\prime(x)&lt;/math&gt;</strong></p>\n<p>&lt;KE id=\"nJABy-dovv1_ZzeHb2MpYgfgTq_s\"&gt; Die Potenzregel für Ableitungen besagt: Für &lt;math&gt;f(x)=x^n&lt;/math&gt; (&lt;math&gt;n \\in \\mathbb{R}&lt;/math&gt; mit &lt;math&gt;n\\neq 0&lt;/math&gt;) gilt &lt;math&gt;f^\\prime(x)=n\\cdot x^{n-1}&lt;/math&gt;.&lt;/KE&gt;</p>\n<p>  </p>\n<p>Um die Potenzregel für Ableitungen verwenden zu können, wandeln wir den Bruch &lt;math&gt;f(x)=\\frac{1}{x^5}&lt;/math&gt; zunächst in eine Potenz um:</p>\n<p>&lt;math&gt;f(x)=\\frac{1}{x^5}&lt;/math&gt;&lt;KE id=\"abUTiDUaheWEjVqypPYzCjN8cHgc\"&gt;&lt;math&gt;\\\\ | \\\\ x^{-n}= \\frac{1}{x^n}&lt;/math&gt; &lt;/KE&gt;</p>\n<p>&lt;math&gt;f(x)=x^{-5}&lt;/math&gt;</p>\n<p>Nun können wir mit der Potenzregel die Ableitung &lt;math&gt;f^\\prime(x)&lt;/math&gt; bestimmen:</p>\n<p>&lt;math&gt;f(x)=x^{-5}&lt;/math&gt;&lt;KE id=\"nJABy-dovv1_ZzeHb2MpYgfgTq_s\"&gt; &lt;math&gt;\\\\ | \\\\ f(x)=x^n \\to f^\\prime(x) = n\\cdot x^{n-1}&lt;/math&gt;&lt;/KE&gt;</p>\n<p>&lt;math&gt;f^\\prime(x)=-5\\cdot x^{-5-1}&lt;/math&gt;</p>\n<p>&lt;math&gt;f^\\prime(x)=-5\\cdot x^{-6}&lt;/math&gt;&lt;KE id=\"abUTiDUaheWEjVqypPYzCjN8cHgc\"&gt;&lt;math&gt;\\\\ | \\\\ x^{-n}= \\frac{1}{x^n}&lt;/math&gt; &lt;/KE&gt;</p>\n<p>&lt;math&gt;f^\\prime(x)=\\frac{-5}{x^{6}} &lt;/math&gt;</p>\n<p>  </p>\n<p><strong>Antwort: Die Ableitung von &lt;math&gt;f(x)=\\frac{1}{x^5}&lt;/math&gt; lautet &lt;math&gt;f^\\prime(x) = \\frac{-5}{x^{6}}&lt;/math&gt;.</strong></p>\n\n    /n  student_solution: \n    \\( f^{\\prime} \\) for \\( f(x)=\\frac{1}{x^{5}} \\) bastirnmen \\[ \\begin{array}{l} f(x)=\\frac{1}{x^{5}}=x^{-5} \\\\ f^{\\prime}(x)=-5 \\cdot x^{-6}=-\\frac{5}{x^{6}} \\end{array} \\]\n\n\n        "#.to_string(),
-                content_type: "text".to_string(),
+                cache_control: None,
+                citations: None,
             }),
             ContentType::Image(ContentImage {
-               source: Source {
-                content_type: "base64".to_string(),
-                data: image_base64,
-                media_type: MediaType::Jpeg,
-               },
-               content_type: "image".to_string(),
+               source: Source::new(image_base64, MediaType::Jpeg),
+               cache_control: None,
             })
         ];
         let messages = vec![Messages {
@@ -538,21 +2403,21 @@ Example 2:
             max_tokens: 1000,
             messages,
             temperature: Some(0.1),
+            ..Default::default()
         };
-        match client.get_message_completed(body).await {
+        match client.get_message_completed(&body).await {
             Ok(res) => {
                 // assert_eq!(res.role, Role::Assistant);
                 println!("{:#?}", res);
             }
             Err(e) => {
-                println!("{:?}", e);
-                assert!(false);
+                panic!("{:?}", e);
             }
         }
     }
     #[test]
     fn test_crater_content_message_text_array() {
-        let prompts = vec!["test1", "test2", "test3"];
+        let prompts = ["test1", "test2", "test3"];
         let content =
             MessageContent::new_content_array_text(prompts.iter().map(|x| x.to_string()).collect());
         if let MessageContent::ContentArray(content) = content {
@@ -563,12 +2428,2324 @@ Example 2:
                         assert_eq!(c.text, format!("test{}", i + 1));
                     }
                     _ => {
-                        assert!(false);
+                        panic!("expected a text block");
                     }
                 }
             }
         } else {
-            assert!(false);
+            panic!("expected a content array");
+        }
+    }
+    #[test]
+    fn test_system_prompt_serialization() {
+        let body = RequestBodyAnthropic::default();
+        let json = serde_json::to_value(&body).unwrap();
+        assert!(json.get("system").is_none());
+
+        let body = RequestBodyAnthropic::default().with_system("Be concise.".to_string());
+        let json = serde_json::to_value(&body).unwrap();
+        assert_eq!(json["system"], "Be concise.");
+
+        let body = RequestBodyAnthropic::default()
+            .with_system_blocks(vec![ContentType::new_text("Be concise.".to_string())]);
+        let json = serde_json::to_value(&body).unwrap();
+        assert_eq!(
+            json["system"],
+            serde_json::json!([{"type": "text", "text": "Be concise."}])
+        );
+    }
+    #[test]
+    fn test_validate_sampling_parameters() {
+        let body = test_support::body().with_top_p(0.9).with_top_k(40);
+        assert!(body.validate().is_ok());
+        let json = serde_json::to_value(&body).unwrap();
+        assert_eq!(json["top_k"], 40);
+
+        let body = test_support::body().with_top_p(1.5);
+        let err = body.validate().unwrap_err();
+        assert!(err.to_string().contains("top_p"));
+
+        let body = test_support::body().with_top_p(f32::NAN);
+        assert!(body.validate().is_err());
+
+        let body = test_support::body().with_top_k(0);
+        let err = body.validate().unwrap_err();
+        assert!(err.to_string().contains("top_k"));
+
+        let json = serde_json::to_value(RequestBodyAnthropic::default()).unwrap();
+        assert!(json.get("top_p").is_none());
+        assert!(json.get("top_k").is_none());
+    }
+    #[test]
+    fn test_stop_sequences_round_trip() {
+        let body = RequestBodyAnthropic::default()
+            .with_stop_sequences(vec!["\n\nHuman:".to_string(), "###END".to_string()]);
+        let json = serde_json::to_string(&body).unwrap();
+        let body: RequestBodyAnthropic = serde_json::from_str(&json).unwrap();
+        let stop_sequences = body.stop_sequences.unwrap();
+        assert_eq!(stop_sequences, vec!["\n\nHuman:", "###END"]);
+
+        let res: ResponseBodyAnthropic = serde_json::from_value(serde_json::json!({
+            "id": "msg_1",
+            "model": "claude-3-5-sonnet-20241022",
+            "role": "assistant",
+            "stop_reason": "stop_sequence",
+            "stop_sequence": "###END",
+            "type": "message",
+            "usage": {"input_tokens": 10, "output_tokens": 5},
+            "content": [{"type": "text", "text": "Done"}]
+        }))
+        .unwrap();
+        assert_eq!(res.stop_reason, StopReason::StopSequence);
+        assert!(stop_sequences.contains(&res.stop_sequence.unwrap()));
+
+        let json = serde_json::to_value(RequestBodyAnthropic::default()).unwrap();
+        assert!(json.get("stop_sequences").is_none());
+    }
+    #[test]
+    fn test_metadata_user_id() {
+        let json = serde_json::to_value(RequestBodyAnthropic::default()).unwrap();
+        assert!(json.get("metadata").is_none());
+
+        let body = RequestBodyAnthropic::default().with_user_id("user-1234");
+        let json = serde_json::to_value(&body).unwrap();
+        assert_eq!(json["metadata"]["user_id"], "user-1234");
+    }
+    #[test]
+    fn test_stop_reason_serde() {
+        for (json, reason) in [
+            ("\"end_turn\"", StopReason::EndTurn),
+            ("\"max_tokens\"", StopReason::MaxTokens),
+            ("\"stop_sequence\"", StopReason::StopSequence),
+            ("\"tool_use\"", StopReason::ToolUse),
+            ("\"pause_turn\"", StopReason::PauseTurn),
+            ("\"refusal\"", StopReason::Refusal),
+            (
+                "\"something_new\"",
+                StopReason::Unknown("something_new".to_string()),
+            ),
+        ] {
+            let parsed: StopReason = serde_json::from_str(json).unwrap();
+            assert_eq!(parsed, reason);
+            assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+        }
+        assert!(StopReason::EndTurn.is_complete());
+        assert!(StopReason::StopSequence.is_complete());
+        assert!(!StopReason::MaxTokens.is_complete());
+        assert!(!StopReason::ToolUse.is_complete());
+        assert!(!StopReason::PauseTurn.is_complete());
+        assert!(!StopReason::Refusal.is_complete());
+        assert!(StopReason::ToolUse.is_tool_use());
+        assert!(!StopReason::EndTurn.is_tool_use());
+        assert!(StopReason::MaxTokens.is_max_tokens());
+        assert!(!StopReason::Unknown("max_token".to_string()).is_max_tokens());
+    }
+    #[test]
+    fn test_service_tier() {
+        let body = RequestBodyAnthropic::default().with_service_tier(ServiceTier::StandardOnly);
+        let json = serde_json::to_value(&body).unwrap();
+        assert_eq!(json["service_tier"], "standard_only");
+
+        let res: ResponseBodyAnthropic = serde_json::from_str(
+            r#"{
+                "id": "msg_1",
+                "model": "claude-3-5-sonnet-20241022",
+                "role": "assistant",
+                "stop_reason": "end_turn",
+                "stop_sequence": null,
+                "type": "message",
+                "usage": {"input_tokens": 12, "output_tokens": 6, "service_tier": "priority"},
+                "content": [{"type": "text", "text": "Paris"}]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(res.usage.service_tier, Some(UsageServiceTier::Priority));
+
+        let usage: Usage =
+            serde_json::from_str(r#"{"input_tokens": 12, "output_tokens": 6}"#).unwrap();
+        assert_eq!(usage.service_tier, None);
+    }
+    #[test]
+    fn test_tool_use() {
+        let body = RequestBodyAnthropic::default().with_tools(vec![Tool {
+            name: "get_weather".to_string(),
+            description: Some("Get the current weather in a given location".to_string()),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {"location": {"type": "string"}},
+                "required": ["location"]
+            }),
+            cache_control: None,
+        }]);
+        let json = serde_json::to_string(&body).unwrap();
+        let body: RequestBodyAnthropic = serde_json::from_str(&json).unwrap();
+        let tools = body.tools.unwrap();
+        assert_eq!(tools[0].name, "get_weather");
+        assert_eq!(tools[0].input_schema["required"][0], "location");
+
+        let res: ResponseBodyAnthropic = serde_json::from_str(
+            r#"{
+                "id": "msg_1",
+                "model": "claude-3-5-sonnet-20241022",
+                "role": "assistant",
+                "stop_reason": "tool_use",
+                "stop_sequence": null,
+                "type": "message",
+                "usage": {"input_tokens": 12, "output_tokens": 6},
+                "content": [
+                    {"type": "text", "text": "Let me check the weather."},
+                    {"type": "tool_use", "id": "toolu_01", "name": "get_weather", "input": {"location": "Paris"}}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(res.stop_reason, StopReason::ToolUse);
+        match &res.content[1] {
+            ContentType::ToolUse(tool_use) => {
+                assert_eq!(tool_use.id, "toolu_01");
+                assert_eq!(tool_use.name, "get_weather");
+                assert_eq!(tool_use.input["location"], "Paris");
+            }
+            other => panic!("unexpected block {:?}", other),
+        }
+
+        let result = ContentType::new_tool_result(
+            "toolu_01".to_string(),
+            MessageContent::new("15 degrees"),
+            None,
+        );
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            serde_json::json!({"type": "tool_result", "tool_use_id": "toolu_01", "content": "15 degrees"})
+        );
+    }
+    #[test]
+    fn test_optional_fields_are_omitted() {
+        let body = RequestBodyAnthropic {
+            temperature: None,
+            messages: vec![Messages::new_user_message_prompt("Hi".to_string())],
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&body).unwrap();
+        let mut keys: Vec<&String> = json.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["max_tokens", "messages", "model"]);
+        assert!(!serde_json::to_string(&body).unwrap().contains("null"));
+
+        let block = ContentToolResult {
+            tool_use_id: "toolu_01".to_string(),
+            content: None,
+            is_error: None,
+        };
+        let json = serde_json::to_value(&block).unwrap();
+        assert!(json.get("content").is_none());
+        assert!(json.get("is_error").is_none());
+
+        let tool = Tool {
+            name: "noop".to_string(),
+            description: None,
+            input_schema: serde_json::json!({"type": "object"}),
+            cache_control: None,
+        };
+        assert!(serde_json::to_value(&tool)
+            .unwrap()
+            .get("description")
+            .is_none());
+        let metadata = serde_json::to_value(Metadata::default()).unwrap();
+        assert_eq!(metadata, serde_json::json!({}));
+    }
+    #[test]
+    fn test_max_tokens_validation() {
+        let json = serde_json::to_value(RequestBodyAnthropic::default()).unwrap();
+        assert!(serde_json::from_value::<RequestBodyAnthropic>(json.clone()).is_ok());
+        let mut json = json;
+        json["max_tokens"] = serde_json::json!(-5);
+        assert!(serde_json::from_value::<RequestBodyAnthropic>(json).is_err());
+
+        let limits = ModelLimits::default();
+        let body = RequestBodyAnthropic::default();
+        assert!(body.validate_model_limits(&limits).is_ok());
+
+        let body = RequestBodyAnthropic {
+            max_tokens: 20000,
+            ..Default::default()
+        };
+        let err = body.validate_model_limits(&limits).unwrap_err();
+        assert!(err.to_string().contains("max_tokens 20000"));
+
+        let body = RequestBodyAnthropic {
+            model: "my-custom-model".to_string(),
+            max_tokens: 20000,
+            ..Default::default()
+        };
+        assert!(body.validate_model_limits(&limits).is_ok());
+        let limits = limits.with_limit("my-custom-model", 1024);
+        assert!(body.validate_model_limits(&limits).is_err());
+    }
+    #[test]
+    fn test_validate_temperature() {
+        for temperature in [0.0, 0.5, 1.0] {
+            let body = RequestBodyAnthropic {
+                temperature: Some(temperature),
+                ..test_support::body()
+            };
+            assert!(body.validate().is_ok());
+        }
+        for temperature in [7.3, -0.5, f32::NAN] {
+            let body = RequestBodyAnthropic {
+                temperature: Some(temperature),
+                ..test_support::body()
+            };
+            match body.validate() {
+                Err(ValidationError::InvalidValue { field, value, .. }) => {
+                    assert_eq!(field, "temperature");
+                    assert_eq!(value, temperature.to_string());
+                }
+                other => panic!("unexpected result {:?}", other),
+            }
         }
     }
+    #[test]
+    fn test_metadata_nested_shape() {
+        let body = RequestBodyAnthropic {
+            temperature: None,
+            ..Default::default()
+        }
+        .with_user_id("13803d75-b4b5-4c3e-b2a2-6f21399b021b".to_string());
+        assert_eq!(
+            serde_json::to_value(&body).unwrap(),
+            serde_json::json!({
+                "model": "claude-3-5-sonnet-20241022",
+                "max_tokens": 1000,
+                "messages": [],
+                "metadata": {"user_id": "13803d75-b4b5-4c3e-b2a2-6f21399b021b"}
+            })
+        );
+        let metadata = Metadata::new("user-1".to_string());
+        assert_eq!(
+            serde_json::to_string(&metadata).unwrap(),
+            r#"{"user_id":"user-1"}"#
+        );
+    }
+    #[test]
+    fn test_cache_control() {
+        let block = ContentType::new_text_cached("A very long rubric".to_string());
+        assert_eq!(
+            serde_json::to_value(&block).unwrap(),
+            serde_json::json!({
+                "type": "text",
+                "text": "A very long rubric",
+                "cache_control": {"type": "ephemeral"}
+            })
+        );
+        let block = ContentType::new_text("Not cached".to_string());
+        assert!(serde_json::to_value(&block)
+            .unwrap()
+            .get("cache_control")
+            .is_none());
+
+        let usage: Usage = serde_json::from_str(
+            r#"{"input_tokens": 21, "output_tokens": 393, "cache_creation_input_tokens": 188086, "cache_read_input_tokens": 0}"#,
+        )
+        .unwrap();
+        assert_eq!(usage.cache_creation_input_tokens, Some(188086));
+        assert_eq!(usage.cache_read_input_tokens, Some(0));
+    }
+    #[test]
+    fn test_usage_total_tokens() {
+        let usage: Usage =
+            serde_json::from_str(r#"{"input_tokens": 12, "output_tokens": 6}"#).unwrap();
+        assert_eq!(usage.cache_creation_input_tokens, None);
+        assert_eq!(usage.cache_read_input_tokens, None);
+        assert_eq!(usage.total_tokens(), 18);
+
+        let usage: Usage = serde_json::from_str(
+            r#"{"input_tokens": 12, "output_tokens": 6, "cache_creation_input_tokens": 0, "cache_read_input_tokens": 1000}"#,
+        )
+        .unwrap();
+        assert_eq!(usage.total_tokens(), 1018);
+    }
+    #[test]
+    fn test_thinking() {
+        let body = RequestBodyAnthropic {
+            max_tokens: 16000,
+            ..test_support::body()
+        }
+        .with_thinking(10000);
+        assert!(body.validate().is_ok());
+        let json = serde_json::to_value(&body).unwrap();
+        assert_eq!(
+            json["thinking"],
+            serde_json::json!({"type": "enabled", "budget_tokens": 10000})
+        );
+        // The default temperature is not sent with thinking enabled
+        assert_eq!(RequestBodyAnthropic::default().temperature, Some(0.1));
+        assert!(json.get("temperature").is_none());
+        assert_eq!(
+            RequestBodyAnthropic::builder()
+                .with_thinking(10000)
+                .temperature,
+            None
+        );
+
+        let thinking_body = body;
+        let body = RequestBodyAnthropic {
+            temperature: Some(0.1),
+            ..thinking_body.clone()
+        };
+        match body.validate() {
+            Err(ValidationError::InvalidValue { field, .. }) => assert_eq!(field, "temperature"),
+            other => panic!("unexpected result {:?}", other),
+        }
+        let body = RequestBodyAnthropic {
+            temperature: Some(1.0),
+            ..thinking_body.clone()
+        };
+        assert!(body.validate().is_ok());
+        let body = thinking_body.with_top_k(40);
+        match body.validate() {
+            Err(ValidationError::InvalidValue { field, .. }) => assert_eq!(field, "top_k"),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(
+            serde_json::to_value(Thinking::Disabled).unwrap(),
+            serde_json::json!({"type": "disabled"})
+        );
+
+        let body = test_support::body().with_thinking(2000);
+        match body.validate() {
+            Err(ValidationError::InvalidValue { field, .. }) => {
+                assert_eq!(field, "thinking.budget_tokens")
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        let body = test_support::body().with_thinking(512);
+        assert!(body.validate().is_err());
+
+        let res: ResponseBodyAnthropic = serde_json::from_str(
+            r#"{
+                "id": "msg_01",
+                "type": "message",
+                "role": "assistant",
+                "model": "claude-3-7-sonnet-20250219",
+                "content": [
+                    {
+                        "type": "thinking",
+                        "thinking": "To find the derivative of 1/x^5 I rewrite it as x^-5...",
+                        "signature": "EqQBCgIYAhIM1gbcDa9GJwZA2b3hGgxBdjrkzLoky3dl1pkiMOYds"
+                    },
+                    {"type": "text", "text": "The derivative is -5/x^6."}
+                ],
+                "stop_reason": "end_turn",
+                "stop_sequence": null,
+                "usage": {"input_tokens": 50, "output_tokens": 120}
+            }"#,
+        )
+        .unwrap();
+        match &res.content[0] {
+            ContentType::Thinking(thinking) => {
+                assert!(thinking.thinking.starts_with("To find the derivative"));
+                assert_eq!(
+                    thinking.signature,
+                    "EqQBCgIYAhIM1gbcDa9GJwZA2b3hGgxBdjrkzLoky3dl1pkiMOYds"
+                );
+            }
+            other => panic!("unexpected block {:?}", other),
+        }
+        assert!(
+            matches!(&res.content[1], ContentType::Text(text) if text.text == "The derivative is -5/x^6.")
+        );
+        assert_eq!(
+            res.thinking(),
+            "To find the derivative of 1/x^5 I rewrite it as x^-5..."
+        );
+        assert_eq!(res.text(), "The derivative is -5/x^6.");
+
+        // content_block_start of a streamed thinking block may carry no signature yet
+        let block: ContentType =
+            serde_json::from_value(serde_json::json!({"type": "thinking", "thinking": ""}))
+                .unwrap();
+        assert!(matches!(block, ContentType::Thinking(thinking) if thinking.signature.is_empty()));
+    }
+    #[test]
+    fn test_invalid_api_key() {
+        let config = Config::new("test-key\n".to_string(), ANTHROPIC_API_URL.to_string());
+        assert!(AnthropicClient::new(config).is_err());
+    }
+    #[test]
+    fn test_cached_system_prompt() {
+        let body = RequestBodyAnthropic::default().with_cached_system(
+            "You are grading essays using this rubric.",
+            "Today is Monday.",
+        );
+        let json = serde_json::to_value(&body).unwrap();
+        assert_eq!(
+            json["system"],
+            serde_json::json!([
+                {
+                    "type": "text",
+                    "text": "You are grading essays using this rubric.",
+                    "cache_control": {"type": "ephemeral"}
+                },
+                {"type": "text", "text": "Today is Monday."}
+            ])
+        );
+        assert_eq!(
+            serde_json::to_value(SystemPrompt::from("Be concise.")).unwrap(),
+            serde_json::json!("Be concise.")
+        );
+    }
+    #[test]
+    fn test_cache_ttl() {
+        for (ttl, json) in [(CacheTtl::FiveMinutes, "5m"), (CacheTtl::OneHour, "1h")] {
+            let block = ContentType::new_text_cached_with_ttl("Rubric".to_string(), ttl);
+            assert_eq!(
+                serde_json::to_value(&block).unwrap(),
+                serde_json::json!({
+                    "type": "text",
+                    "text": "Rubric",
+                    "cache_control": {"type": "ephemeral", "ttl": json}
+                })
+            );
+            let cache_control: CacheControl =
+                serde_json::from_value(serde_json::json!({"type": "ephemeral", "ttl": json}))
+                    .unwrap();
+            assert_eq!(cache_control, CacheControl::ephemeral_with_ttl(ttl));
+        }
+        assert_eq!(
+            serde_json::to_value(CacheControl::ephemeral()).unwrap(),
+            serde_json::json!({"type": "ephemeral"})
+        );
+        assert!(serde_json::from_value::<CacheControl>(
+            serde_json::json!({"type": "ephemeral", "ttl": "2h"})
+        )
+        .is_err());
+    }
+    #[test]
+    fn test_with_model() {
+        let body = RequestBodyAnthropic::default().with_model(ClaudeModel::ClaudeSonnet4);
+        assert_eq!(body.model, "claude-sonnet-4-20250514");
+        let body = RequestBodyAnthropic::default().with_model("my-fine-tuned-model");
+        assert_eq!(body.model, "my-fine-tuned-model");
+        let body = RequestBodyAnthropic::new(ClaudeModel::Claude35Haiku, 100, vec![], None);
+        assert_eq!(body.model, "claude-3-5-haiku-20241022");
+        assert_eq!(
+            RequestBodyAnthropic::default().model,
+            "claude-3-5-sonnet-20241022"
+        );
+    }
+    #[test]
+    fn test_source_from_file() {
+        let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/pixel.png");
+        match Source::from_file(fixture).unwrap() {
+            Source::Base64 { media_type, data } => {
+                assert_eq!(media_type, MediaType::Png);
+                assert_eq!(
+                    STANDARD.decode(data).unwrap(),
+                    std::fs::read(fixture).unwrap()
+                );
+            }
+            other => panic!("unexpected source {:?}", other),
+        }
+        assert_eq!(
+            Source::from_path(fixture).unwrap(),
+            Source::from_file(fixture).unwrap()
+        );
+
+        assert_eq!(
+            MediaType::from_bytes(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            Some(MediaType::Jpeg)
+        );
+        assert_eq!(MediaType::from_bytes(b"GIF89a..."), Some(MediaType::Gif));
+        assert_eq!(
+            MediaType::from_bytes(b"RIFF\x24\x00\x00\x00WEBPVP8 "),
+            Some(MediaType::Webp)
+        );
+        assert_eq!(MediaType::from_bytes(b"%PDF-1.7"), None);
+        assert_eq!(MediaType::from_extension("JPG"), Some(MediaType::Jpeg));
+        assert_eq!(MediaType::from_extension("bmp"), None);
+
+        let unknown = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/blank.pdf");
+        assert!(Source::from_file(unknown).is_err());
+        let missing = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/missing.png");
+        assert!(Source::from_file(missing).is_err());
+    }
+    #[test]
+    fn test_validate_messages() {
+        assert!(test_support::body().validate_messages().is_ok());
+        let err = RequestBodyAnthropic::default().validate().unwrap_err();
+        assert!(err.to_string().contains("at least one message"));
+
+        let body = RequestBodyAnthropic {
+            messages: vec![Messages::new_assistant_message_prompt("Hi".to_string())],
+            ..Default::default()
+        };
+        assert_eq!(
+            body.validate(),
+            Err(ValidationError::InvalidMessage {
+                index: 0,
+                reason: "the first message must be from the user".to_string(),
+            })
+        );
+
+        let body = RequestBodyAnthropic {
+            messages: vec![
+                Messages::new_user_message_prompt("Hi".to_string()),
+                Messages::new_assistant_message_prompt("".to_string()),
+            ],
+            ..Default::default()
+        };
+        let err = body.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid message at index 1: content must not be empty"
+        );
+
+        for content in [
+            MessageContent::ContentArray(vec![]),
+            MessageContent::new_content_array_text(vec!["Hi".to_string(), "".to_string()]),
+        ] {
+            let body = RequestBodyAnthropic {
+                messages: vec![Messages::new(Role::User, content)],
+                ..Default::default()
+            };
+            assert!(matches!(
+                body.validate(),
+                Err(ValidationError::InvalidMessage { index: 0, .. })
+            ));
+        }
+    }
+    #[test]
+    fn test_image_url() {
+        let block = ContentType::new_image_url("https://example.com/cat.png".to_string());
+        let json = serde_json::to_value(&block).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "image",
+                "source": {"type": "url", "url": "https://example.com/cat.png"}
+            })
+        );
+        match serde_json::from_value::<ContentType>(json).unwrap() {
+            ContentType::Image(image) => {
+                assert_eq!(
+                    image.source,
+                    Source::new_url("https://example.com/cat.png".to_string())
+                )
+            }
+            other => panic!("unexpected block {:?}", other),
+        }
+
+        let source = Source::new("aGVsbG8=".to_string(), MediaType::Png);
+        let json = serde_json::to_value(&source).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"type": "base64", "data": "aGVsbG8=", "media_type": "image/png"})
+        );
+        assert_eq!(serde_json::from_value::<Source>(json).unwrap(), source);
+
+        // Image blocks echoed back, e.g. in a tool result, keep their source
+        let content: MessageContent = serde_json::from_value(serde_json::json!([
+            {
+                "type": "image",
+                "source": {"type": "base64", "media_type": "image/png", "data": "aGVsbG8="}
+            },
+            {
+                "type": "image",
+                "source": {"type": "url", "url": "https://example.com/cat.png"}
+            }
+        ]))
+        .unwrap();
+        assert_eq!(
+            content,
+            MessageContent::ContentArray(vec![
+                ContentType::new_image(source),
+                ContentType::new_image_url("https://example.com/cat.png".to_string()),
+            ])
+        );
+    }
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let body = RequestBodyAnthropic {
+            model: "".to_string(),
+            max_tokens: 0,
+            temperature: Some(2.0),
+            stop_sequences: Some(vec!["END".to_string(), " ".to_string()]),
+            ..Default::default()
+        };
+        let err = body.validate().unwrap_err();
+        let fields: Vec<String> = err
+            .errors()
+            .iter()
+            .map(|error| match error {
+                ValidationError::InvalidValue { field, .. } => field.to_string(),
+                other => other.to_string(),
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                "messages",
+                "model",
+                "max_tokens",
+                "temperature",
+                "stop_sequences"
+            ]
+        );
+        assert!(err.to_string().starts_with("5 validation errors: "));
+
+        // A single problem is returned as is
+        let err = test_support::body().with_top_k(0).validate().unwrap_err();
+        assert_eq!(err.errors().len(), 1);
+        assert!(matches!(
+            err,
+            ValidationError::InvalidValue { field: "top_k", .. }
+        ));
+    }
+    #[test]
+    fn test_pdf_document() {
+        let block = ContentType::new_pdf("JVBERi0xLjcK".to_string());
+        assert_eq!(
+            serde_json::to_value(&block).unwrap(),
+            serde_json::json!({
+                "type": "document",
+                "source": {
+                    "type": "base64",
+                    "media_type": "application/pdf",
+                    "data": "JVBERi0xLjcK"
+                }
+            })
+        );
+
+        let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/blank.pdf");
+        match ContentType::from_pdf_file(fixture).unwrap() {
+            ContentType::Document(ContentDocument {
+                source: DocumentSource::Base64 { media_type, data },
+                ..
+            }) => {
+                assert_eq!(media_type, DocumentMediaType::Pdf);
+                let data = STANDARD.decode(data).unwrap();
+                assert_eq!(data, std::fs::read(fixture).unwrap());
+            }
+            other => panic!("unexpected block {:?}", other),
+        }
+        let image = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/pixel.png");
+        assert!(ContentType::from_pdf_file(image).is_err());
+    }
+    #[tokio::test]
+    async fn test_send_cloned_request() {
+        use wiremock::{
+            matchers::{body_json, method, path},
+            Mock, MockServer,
+        };
+
+        let template = test_support::body().with_system("Be concise.".to_string());
+        let body = template.clone();
+        assert_eq!(body, template);
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(body_json(&template))
+            .respond_with(test_support::message_response())
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client =
+            AnthropicClient::new(Config::new("test-key".to_string(), server.uri())).unwrap();
+        let expected = ResponseBodyAnthropic {
+            id: "msg_1".to_string(),
+            model: "claude-sonnet-4-20250514".to_string(),
+            role: Role::Assistant,
+            stop_reason: StopReason::EndTurn,
+            stop_sequence: None,
+            message_type: "message".to_string(),
+            usage: Usage {
+                input_tokens: 10,
+                output_tokens: 5,
+                service_tier: None,
+                cache_creation_input_tokens: None,
+                cache_read_input_tokens: None,
+                server_tool_use: None,
+                extra: HashMap::new(),
+            },
+            content: vec![ContentType::new_text("Hi".to_string())],
+            container: None,
+            extra: HashMap::new(),
+        };
+        for body in [body, template] {
+            assert_eq!(client.get_message_completed(&body).await.unwrap(), expected);
+        }
+    }
+    #[test]
+    fn test_messages_conversions() {
+        let body = RequestBodyAnthropic::builder()
+            .messages(["hi", "there"])
+            .with_max_tokens(100);
+        assert_eq!(body.max_tokens, 100);
+        assert_eq!(body.messages.len(), 2);
+        assert!(body
+            .messages
+            .iter()
+            .all(|message| message.role == Role::User));
+        assert_eq!(body.messages[1].content, MessageContent::new("there"));
+
+        let prompts = ["What is 2 + 2?".to_string(), "4".to_string()];
+        let turns = prompts
+            .iter()
+            .zip([Role::User, Role::Assistant])
+            .map(|(prompt, role)| (role, prompt.clone()));
+        let body =
+            RequestBodyAnthropic::from_messages(ClaudeModel::ClaudeSonnet4, 100, turns, None);
+        assert_eq!(body.messages[0].role, Role::User);
+        assert_eq!(body.messages[1].role, Role::Assistant);
+        assert_eq!(body.messages[1].content, MessageContent::new("4"));
+
+        let message: Messages = "Hello".to_string().into();
+        assert_eq!(message.role, Role::User);
+    }
+    #[test]
+    fn test_tool_uses() {
+        let res: ResponseBodyAnthropic = serde_json::from_str(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/tool_use_response.json"
+        )))
+        .unwrap();
+        assert_eq!(res.stop_reason, StopReason::ToolUse);
+        assert_eq!(res.content.len(), 3);
+        assert!(matches!(res.content[0], ContentType::Text(_)));
+
+        let tool_uses = res.tool_uses();
+        assert_eq!(tool_uses.len(), 2);
+        assert_eq!(tool_uses[0].id, "toolu_01A09q90qw90lq917835lq9");
+        assert_eq!(tool_uses[0].name, "get_weather");
+        assert_eq!(tool_uses[0].input["location"], "San Francisco, CA");
+        assert_eq!(tool_uses[1].input["location"], "New York, NY");
+    }
+    #[test]
+    fn test_response_text() {
+        let res: ResponseBodyAnthropic = serde_json::from_value(serde_json::json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-20250514",
+            "content": [
+                {"type": "text", "text": "Let me check. "},
+                {"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {}},
+                {"type": "text", "text": "It is sunny."}
+            ],
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 10, "output_tokens": 20}
+        }))
+        .unwrap();
+        assert_eq!(res.text(), "Let me check. It is sunny.");
+        assert_eq!(res.first_text(), Some("Let me check. "));
+
+        let res = ResponseBodyAnthropic {
+            content: res
+                .tool_uses()
+                .into_iter()
+                .cloned()
+                .map(ContentType::ToolUse)
+                .collect(),
+            ..res
+        };
+        assert_eq!(res.text(), "");
+        assert_eq!(res.first_text(), None);
+    }
+    #[test]
+    fn test_tool_result_message() {
+        let message = Messages::new_tool_result_message(
+            "toolu_01".to_string(),
+            MessageContent::new("15 degrees"),
+            None,
+        );
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::json!({
+                "role": "user",
+                "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_01", "content": "15 degrees"}
+                ]
+            })
+        );
+
+        let message = Messages::new_tool_result_message(
+            "toolu_02".to_string(),
+            MessageContent::ContentArray(vec![
+                ContentType::new_text("Chart of the forecast".to_string()),
+                ContentType::new_image(Source::new("iVBORw0KGgo=".to_string(), MediaType::Png)),
+            ]),
+            Some(false),
+        );
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "role": "user",
+                "content": [{
+                    "type": "tool_result",
+                    "tool_use_id": "toolu_02",
+                    "is_error": false,
+                    "content": [
+                        {"type": "text", "text": "Chart of the forecast"},
+                        {
+                            "type": "image",
+                            "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}
+                        }
+                    ]
+                }]
+            })
+        );
+        assert_eq!(serde_json::from_value::<Messages>(json).unwrap(), message);
+
+        let message = Messages::new_tool_result_message(
+            "toolu_03".to_string(),
+            MessageContent::new("City not found"),
+            Some(true),
+        );
+        assert_eq!(
+            serde_json::to_value(&message).unwrap()["content"][0]["is_error"],
+            true
+        );
+    }
+    #[test]
+    fn test_thinking_blocks_round_trip() {
+        let fixture = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/thinking_response.json"
+        ));
+        let res: ResponseBodyAnthropic = serde_json::from_str(fixture).unwrap();
+        assert_eq!(res.content.len(), 3);
+        assert!(
+            matches!(&res.content[0], ContentType::Thinking(thinking) if thinking.signature.starts_with("EuYBCkQYAiJA"))
+        );
+        assert!(
+            matches!(&res.content[1], ContentType::RedactedThinking(redacted) if redacted.data.starts_with("EmwKAhgBEgy3"))
+        );
+        assert_eq!(
+            res.first_text(),
+            Some("Yes, 1729 is the Hardy-Ramanujan number: 1^3 + 12^3 = 9^3 + 10^3.")
+        );
+
+        assert!(res
+            .thinking()
+            .starts_with("The user wants to know if 1729 is special."));
+        // The blocks are echoed back unchanged in the next request
+        let assistant = Messages::new(
+            Role::Assistant,
+            MessageContent::ContentArray(res.content.clone()),
+        );
+        let json = serde_json::to_value(&assistant).unwrap();
+        let fixture: serde_json::Value = serde_json::from_str(fixture).unwrap();
+        assert_eq!(json["content"], fixture["content"]);
+    }
+    #[test]
+    fn test_validate_boundaries() {
+        let body = |temperature: f32, max_tokens: u32| RequestBodyAnthropic {
+            temperature: Some(temperature),
+            max_tokens,
+            ..test_support::body()
+        };
+        assert!(body(0.0, 1).validate().is_ok());
+        assert!(body(1.0, 1).validate().is_ok());
+        assert!(body(1.0 + f32::EPSILON, 1).validate().is_err());
+        assert!(body(-f32::EPSILON, 1).validate().is_err());
+        assert_eq!(
+            body(0.0, 0).validate(),
+            Err(ValidationError::InvalidValue {
+                field: "max_tokens",
+                value: "0".to_string(),
+                reason: "must be greater than 0".to_string(),
+            })
+        );
+
+        let body = RequestBodyAnthropic {
+            messages: vec![],
+            ..test_support::body()
+        };
+        assert!(body.validate_messages().is_err());
+        assert!(test_support::body().validate_messages().is_ok());
+    }
+    #[test]
+    fn test_document_sources() {
+        let block = ContentType::new_document(
+            DocumentSource::new_text("The grass is green. The sky is blue.".to_string()),
+            Some("Colors".to_string()),
+            Some("Written by a child".to_string()),
+        );
+        let json = serde_json::json!({
+            "type": "document",
+            "source": {
+                "type": "text",
+                "media_type": "text/plain",
+                "data": "The grass is green. The sky is blue."
+            },
+            "title": "Colors",
+            "context": "Written by a child"
+        });
+        assert_eq!(serde_json::to_value(&block).unwrap(), json);
+        assert_eq!(serde_json::from_value::<ContentType>(json).unwrap(), block);
+
+        let block = ContentType::new_document(
+            DocumentSource::new_content(vec![
+                ContentType::new_text("The grass is green.".to_string()),
+                ContentType::new_text("The sky is blue.".to_string()),
+            ]),
+            None,
+            None,
+        );
+        let json = serde_json::json!({
+            "type": "document",
+            "source": {
+                "type": "content",
+                "content": [
+                    {"type": "text", "text": "The grass is green."},
+                    {"type": "text", "text": "The sky is blue."}
+                ]
+            }
+        });
+        assert_eq!(serde_json::to_value(&block).unwrap(), json);
+        assert_eq!(serde_json::from_value::<ContentType>(json).unwrap(), block);
+
+        let source = DocumentSource::new_pdf("JVBERi0xLjcK".to_string());
+        assert_eq!(
+            serde_json::to_value(&source).unwrap(),
+            serde_json::json!({
+                "type": "base64",
+                "media_type": "application/pdf",
+                "data": "JVBERi0xLjcK"
+            })
+        );
+    }
+    #[test]
+    fn test_document_url() {
+        let block =
+            ContentType::new_document_url("https://example.com/report.pdf".to_string()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&block).unwrap(),
+            serde_json::json!({
+                "type": "document",
+                "source": {"type": "url", "url": "https://example.com/report.pdf"}
+            })
+        );
+
+        for url in ["ftp://example.com/report.pdf", "report.pdf", ""] {
+            match ContentType::new_document_url(url.to_string()) {
+                Err(ValidationError::InvalidValue { field, value, .. }) => {
+                    assert_eq!(field, "source.url");
+                    assert_eq!(value, url);
+                }
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
+    #[test]
+    fn test_file_sources() {
+        let image = ContentType::new_image(Source::new_file("file_abc".to_string()));
+        assert_eq!(
+            serde_json::to_value(&image).unwrap(),
+            serde_json::json!({
+                "type": "image",
+                "source": {"type": "file", "file_id": "file_abc"}
+            })
+        );
+        let document = ContentType::new_document(
+            DocumentSource::new_file("file_def".to_string()),
+            Some("Report".to_string()),
+            None,
+        );
+        assert_eq!(
+            serde_json::to_value(&document).unwrap(),
+            serde_json::json!({
+                "type": "document",
+                "source": {"type": "file", "file_id": "file_def"},
+                "title": "Report"
+            })
+        );
+
+        assert!(image.references_file());
+        assert!(
+            !ContentType::new_image_url("https://example.com/cat.png".to_string())
+                .references_file()
+        );
+        let tool_result = ContentType::new_tool_result(
+            "toolu_1".to_string(),
+            MessageContent::ContentArray(vec![image]),
+            None,
+        );
+        assert!(tool_result.references_file());
+        let body = test_support::body().messages([Messages::new(
+            Role::User,
+            MessageContent::ContentArray(vec![tool_result]),
+        )]);
+        assert!(body.uses_files_api());
+        assert!(!test_support::body().uses_files_api());
+    }
+    #[test]
+    fn test_citations() {
+        let block = ContentType::new_document(
+            DocumentSource::new_text("The grass is green.".to_string()),
+            Some("Colors".to_string()),
+            None,
+        )
+        .with_citations();
+        assert_eq!(
+            serde_json::to_value(&block).unwrap()["citations"],
+            serde_json::json!({"enabled": true})
+        );
+
+        let res: ResponseBodyAnthropic = serde_json::from_str(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/citations_response.json"
+        )))
+        .unwrap();
+        assert_eq!(
+            res.text(),
+            "Based on the documents, the grass is green, the annual revenue grew by 12% and the sky is blue."
+        );
+        let citations = res.citations();
+        assert_eq!(citations.len(), 3);
+        assert_eq!(citations[0].0, "the grass is green");
+        assert_eq!(
+            citations[0].1,
+            &Citation::CharLocation {
+                cited_text: "The grass is green.".to_string(),
+                document_index: 0,
+                document_title: Some("Colors".to_string()),
+                start_char_index: 0,
+                end_char_index: 20,
+            }
+        );
+        assert!(matches!(
+            citations[1].1,
+            Citation::PageLocation {
+                start_page_number: 3,
+                end_page_number: 4,
+                ..
+            }
+        ));
+        assert_eq!(citations[1].1.document_title(), Some("Annual report"));
+        assert!(matches!(
+            citations[2].1,
+            Citation::ContentBlockLocation {
+                start_block_index: 1,
+                end_block_index: 2,
+                ..
+            }
+        ));
+        assert_eq!(citations[2].1.cited_text(), "The sky is blue.");
+        assert_eq!(citations[2].1.document_index(), Some(2));
+        assert_eq!(citations[2].1.document_title(), None);
+    }
+    #[test]
+    fn test_search_result() {
+        let block = ContentType::new_search_result(
+            "https://docs.example.com/api".to_string(),
+            "API reference".to_string(),
+            vec!["Requests are limited to 50 per minute.".to_string()],
+        )
+        .with_citations();
+        let json = serde_json::json!({
+            "type": "search_result",
+            "source": "https://docs.example.com/api",
+            "title": "API reference",
+            "content": [{"type": "text", "text": "Requests are limited to 50 per minute."}],
+            "citations": {"enabled": true}
+        });
+        assert_eq!(serde_json::to_value(&block).unwrap(), json);
+        assert_eq!(serde_json::from_value::<ContentType>(json).unwrap(), block);
+
+        // Search results are sent back as the content of a tool result
+        let message = Messages::new_tool_result_message(
+            "toolu_1".to_string(),
+            MessageContent::ContentArray(vec![block.clone()]),
+            None,
+        );
+        let json = serde_json::to_string(&message).unwrap();
+        let message: Messages = serde_json::from_str(&json).unwrap();
+        match &message.content {
+            MessageContent::ContentArray(blocks) => match &blocks[0] {
+                ContentType::ToolResult(result) => assert_eq!(
+                    result.content,
+                    Some(MessageContent::ContentArray(vec![block]))
+                ),
+                other => panic!("unexpected block {:?}", other),
+            },
+            other => panic!("unexpected content {:?}", other),
+        }
+    }
+    #[test]
+    fn test_search_result_citations() {
+        let fixture = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/search_result_citations_response.json"
+        ));
+        let res: ResponseBodyAnthropic = serde_json::from_str(fixture).unwrap();
+        assert_eq!(res.text(), "Requests are rate limited to 50 per minute.");
+        let citations = res.citations();
+        assert_eq!(citations.len(), 2);
+        assert_eq!(
+            citations[0].1,
+            &Citation::SearchResultLocation {
+                cited_text: "Requests are limited to 50 per minute.".to_string(),
+                source: "https://docs.example.com/api".to_string(),
+                title: Some("API reference".to_string()),
+                search_result_index: 0,
+                start_block_index: 0,
+                end_block_index: 1,
+            }
+        );
+        assert_eq!(citations[0].1.document_title(), Some("API reference"));
+        assert_eq!(citations[0].1.document_index(), None);
+        assert!(matches!(citations[1].1, Citation::Unknown(_)));
+        assert_eq!(citations[1].1.cited_text(), "Limits are per workspace.");
+
+        // Both citations are written back unchanged
+        let json: serde_json::Value = serde_json::from_str(fixture).unwrap();
+        assert_eq!(serde_json::to_value(&res).unwrap(), json);
+    }
+    #[test]
+    fn test_web_search_tool_result() {
+        let res: ResponseBodyAnthropic = serde_json::from_str(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/web_search_response.json"
+        )))
+        .unwrap();
+        assert_eq!(res.content.len(), 4);
+        match &res.content[2] {
+            ContentType::WebSearchToolResult(result) => {
+                assert_eq!(result.tool_use_id, "srvtoolu_01WYG3ziw53XMcoyKL4XcZmE");
+                match &result.content {
+                    WebSearchToolResultContent::Results(results) => {
+                        assert_eq!(results[0].title, "Claude Shannon - Wikipedia");
+                        assert_eq!(results[0].page_age.as_deref(), Some("April 30, 2025"));
+                        assert_eq!(results[1].page_age, None);
+                    }
+                    other => panic!("unexpected content {:?}", other),
+                }
+            }
+            other => panic!("unexpected block {:?}", other),
+        }
+        assert_eq!(
+            res.searched_urls(),
+            vec![
+                "https://en.wikipedia.org/wiki/Claude_Shannon",
+                "https://www.britannica.com/biography/Claude-Shannon"
+            ]
+        );
+        let citations = res.citations();
+        assert_eq!(citations.len(), 1);
+        assert_eq!(citations[0].1.document_index(), None);
+        assert_eq!(
+            citations[0].1.document_title(),
+            Some("Claude Shannon - Wikipedia")
+        );
+
+        let res: ResponseBodyAnthropic = serde_json::from_str(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/web_search_error_response.json"
+        )))
+        .unwrap();
+        match &res.content[1] {
+            ContentType::WebSearchToolResult(result) => assert_eq!(
+                result.content,
+                WebSearchToolResultContent::Error(WebSearchToolResultError {
+                    error_code: "max_uses_exceeded".to_string(),
+                    error_type: "web_search_tool_result_error".to_string(),
+                })
+            ),
+            other => panic!("unexpected block {:?}", other),
+        }
+        assert!(res.searched_urls().is_empty());
+        assert_eq!(res.text(), "I could not search the web any further.");
+    }
+    #[test]
+    fn test_server_tool_use() {
+        let fixture = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/web_search_response.json"
+        ));
+        let res: ResponseBodyAnthropic = serde_json::from_str(fixture).unwrap();
+        assert!(res.tool_uses().is_empty());
+        let server_tool_uses = res.server_tool_uses();
+        assert_eq!(server_tool_uses.len(), 1);
+        assert_eq!(server_tool_uses[0].id, "srvtoolu_01WYG3ziw53XMcoyKL4XcZmE");
+        assert_eq!(server_tool_uses[0].name, "web_search");
+        assert_eq!(
+            server_tool_uses[0].input["query"],
+            "claude shannon birth date"
+        );
+
+        // The assistant turn is sent back unchanged
+        let assistant = Messages::new(
+            Role::Assistant,
+            MessageContent::ContentArray(res.content.clone()),
+        );
+        let json = serde_json::to_value(&assistant).unwrap();
+        let fixture: serde_json::Value = serde_json::from_str(fixture).unwrap();
+        assert_eq!(json["content"], fixture["content"]);
+        let assistant: Messages = serde_json::from_value(json).unwrap();
+        assert_eq!(assistant.content, MessageContent::ContentArray(res.content));
+
+        let tool_use: ContentType = serde_json::from_value(serde_json::json!({
+            "type": "tool_use",
+            "id": "toolu_1",
+            "name": "get_weather",
+            "input": {}
+        }))
+        .unwrap();
+        assert!(matches!(tool_use, ContentType::ToolUse(_)));
+    }
+    #[test]
+    fn test_mixed_thinking_blocks() {
+        let res: ResponseBodyAnthropic = serde_json::from_value(serde_json::json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-3-7-sonnet-20250219",
+            "content": [
+                {"type": "thinking", "thinking": "First step. ", "signature": "sig_1"},
+                {"type": "redacted_thinking", "data": "EmwKAhgBEgy3va3pzix"},
+                {"type": "redacted_thinking", "data": "EmwKAhgBEgyKZfHJG4"},
+                {"type": "thinking", "thinking": "Second step.", "signature": "sig_2"},
+                {"type": "text", "text": "Done."}
+            ],
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 10, "output_tokens": 20}
+        }))
+        .unwrap();
+        let kinds: Vec<&str> = res
+            .content
+            .iter()
+            .map(|block| match block {
+                ContentType::Thinking(_) => "thinking",
+                ContentType::RedactedThinking(_) => "redacted_thinking",
+                ContentType::Text(_) => "text",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "thinking",
+                "redacted_thinking",
+                "redacted_thinking",
+                "thinking",
+                "text"
+            ]
+        );
+        assert!(
+            matches!(&res.content[2], ContentType::RedactedThinking(redacted) if redacted.data == "EmwKAhgBEgyKZfHJG4")
+        );
+        assert_eq!(res.thinking(), "First step. Second step.");
+        assert_eq!(res.text(), "Done.");
+    }
+    #[test]
+    fn test_content_type_wire_format() {
+        let cases = vec![
+            (
+                ContentType::new_text("Hi".to_string()),
+                serde_json::json!({"type": "text", "text": "Hi"}),
+            ),
+            (
+                ContentType::new_text_cached("Rubric".to_string()),
+                serde_json::json!({
+                    "type": "text",
+                    "text": "Rubric",
+                    "cache_control": {"type": "ephemeral"}
+                }),
+            ),
+            (
+                ContentType::new_image(Source::new("aGVsbG8=".to_string(), MediaType::Png)),
+                serde_json::json!({
+                    "type": "image",
+                    "source": {"type": "base64", "media_type": "image/png", "data": "aGVsbG8="}
+                }),
+            ),
+            (
+                ContentType::new_tool_use(
+                    "toolu_1".to_string(),
+                    "get_weather".to_string(),
+                    serde_json::json!({"location": "Paris"}),
+                ),
+                serde_json::json!({
+                    "type": "tool_use",
+                    "id": "toolu_1",
+                    "name": "get_weather",
+                    "input": {"location": "Paris"}
+                }),
+            ),
+            (
+                ContentType::ServerToolUse(ContentServerToolUse {
+                    id: "srvtoolu_1".to_string(),
+                    name: "web_search".to_string(),
+                    input: serde_json::json!({"query": "rust"}),
+                }),
+                serde_json::json!({
+                    "type": "server_tool_use",
+                    "id": "srvtoolu_1",
+                    "name": "web_search",
+                    "input": {"query": "rust"}
+                }),
+            ),
+            (
+                ContentType::new_tool_result(
+                    "toolu_1".to_string(),
+                    MessageContent::new("Sunny"),
+                    Some(false),
+                ),
+                serde_json::json!({
+                    "type": "tool_result",
+                    "tool_use_id": "toolu_1",
+                    "content": "Sunny",
+                    "is_error": false
+                }),
+            ),
+            (
+                ContentType::Thinking(ContentThinking {
+                    thinking: "Let me think.".to_string(),
+                    signature: "sig".to_string(),
+                }),
+                serde_json::json!({"type": "thinking", "thinking": "Let me think.", "signature": "sig"}),
+            ),
+            (
+                ContentType::RedactedThinking(ContentRedactedThinking {
+                    data: "EmwKAhgB".to_string(),
+                }),
+                serde_json::json!({"type": "redacted_thinking", "data": "EmwKAhgB"}),
+            ),
+            (
+                ContentType::new_pdf("JVBERi0xLjcK".to_string()),
+                serde_json::json!({
+                    "type": "document",
+                    "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBERi0xLjcK"}
+                }),
+            ),
+            (
+                ContentType::new_search_result(
+                    "https://example.com".to_string(),
+                    "Example".to_string(),
+                    vec!["Result".to_string()],
+                ),
+                serde_json::json!({
+                    "type": "search_result",
+                    "source": "https://example.com",
+                    "title": "Example",
+                    "content": [{"type": "text", "text": "Result"}]
+                }),
+            ),
+            (
+                ContentType::WebSearchToolResult(ContentWebSearchToolResult {
+                    tool_use_id: "srvtoolu_1".to_string(),
+                    content: WebSearchToolResultContent::Error(WebSearchToolResultError {
+                        error_code: "too_many_requests".to_string(),
+                        error_type: "web_search_tool_result_error".to_string(),
+                    }),
+                }),
+                serde_json::json!({
+                    "type": "web_search_tool_result",
+                    "tool_use_id": "srvtoolu_1",
+                    "content": {"type": "web_search_tool_result_error", "error_code": "too_many_requests"}
+                }),
+            ),
+        ];
+        for (block, json) in cases {
+            assert_eq!(serde_json::to_value(&block).unwrap(), json);
+            assert_eq!(serde_json::from_value::<ContentType>(json).unwrap(), block);
+        }
+    }
+    #[test]
+    fn test_malformed_content_block() {
+        let err = serde_json::from_value::<ContentType>(serde_json::json!({
+            "type": "tool_use",
+            "id": "toolu_1",
+            "input": {}
+        }))
+        .unwrap_err();
+        assert!(err.to_string().contains("missing field `name`"), "{}", err);
+
+        // An unknown type is kept as is, see test_unknown_content_block
+        let block = serde_json::from_value::<ContentType>(serde_json::json!({
+            "type": "hologram",
+            "text": "Hi"
+        }))
+        .unwrap();
+        assert!(matches!(block, ContentType::Unknown(_)));
+
+        let err =
+            serde_json::from_value::<ContentType>(serde_json::json!({"text": "Hi"})).unwrap_err();
+        assert!(err.to_string().contains("missing field `type`"), "{}", err);
+
+        // A malformed block fails the whole response instead of becoming an empty text block
+        let err = serde_json::from_value::<ResponseBodyAnthropic>(serde_json::json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-20250514",
+            "content": [{"type": "text"}],
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 10, "output_tokens": 20}
+        }))
+        .unwrap_err();
+        assert!(err.to_string().contains("missing field `text`"), "{}", err);
+    }
+    #[test]
+    fn test_source_from_bytes() {
+        let png = [
+            137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 1, 0, 0, 0, 1,
+            8, 2, 0, 0, 0, 144, 119, 83, 222, 0, 0, 0, 12, 73, 68, 65, 84, 120, 156, 99, 248, 207,
+            192, 0, 0, 3, 1, 1, 0, 201, 254, 146, 239, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96,
+            130,
+        ];
+        let block = ContentType::new_image_from_bytes(&png, MediaType::Png).unwrap();
+        assert_eq!(
+            block,
+            ContentType::new_image(Source::new(
+                "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAIAAACQd1PeAAAADElEQVR4nGP4z8AAAAMBAQDJ/pLvAAAAAElFTkSuQmCC"
+                    .to_string(),
+                MediaType::Png
+            ))
+        );
+
+        match Source::from_bytes(&[], MediaType::Png) {
+            Err(ValidationError::InvalidValue { field, .. }) => assert_eq!(field, "source.data"),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+    #[tokio::test]
+    async fn test_image_from_file() {
+        let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/pixel.png");
+        let block = ContentType::new_image_from_file(fixture).unwrap();
+        assert_eq!(
+            block,
+            ContentType::new_image_from_bytes(&std::fs::read(fixture).unwrap(), MediaType::Png)
+                .unwrap()
+        );
+        assert_eq!(
+            Source::from_file_async(fixture).await.unwrap(),
+            Source::from_file(fixture).unwrap()
+        );
+        assert_eq!(
+            Source::from_path_async(fixture).await.unwrap(),
+            Source::from_file(fixture).unwrap()
+        );
+
+        let unsupported = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/tool_use_response.json"
+        );
+        let err = ContentType::new_image_from_file(unsupported).unwrap_err();
+        assert!(err.to_string().starts_with("Unsupported image format"));
+        let missing = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/missing.png");
+        assert!(ContentType::new_image_from_file(missing).is_err());
+        assert!(Source::from_file_async(missing).await.is_err());
+    }
+    #[tokio::test]
+    async fn test_source_from_url() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let png = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/pixel.png"
+        ))
+        .unwrap();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/pixel"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(png.clone(), "image/png"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/pixel.png"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(png.clone(), "application/octet-stream"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/page"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("<html></html>", "text/html"))
+            .mount(&server)
+            .await;
+
+        let expected = Source::from_bytes(&png, MediaType::Png).unwrap();
+        let url = format!("{}/pixel", server.uri());
+        let source = Source::from_url(&url, None, DEFAULT_MAX_IMAGE_BYTES)
+            .await
+            .unwrap();
+        assert_eq!(source, expected);
+        let client = reqwest::Client::new();
+        let url = format!("{}/pixel.png", server.uri());
+        let source = Source::from_url(&url, Some(&client), DEFAULT_MAX_IMAGE_BYTES)
+            .await
+            .unwrap();
+        assert_eq!(source, expected);
+
+        let url = format!("{}/page", server.uri());
+        match Source::from_url(&url, None, DEFAULT_MAX_IMAGE_BYTES).await {
+            Err(ImageDownloadError::UnsupportedContentType(content_type)) => {
+                assert_eq!(content_type, "text/html")
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        let url = format!("{}/pixel", server.uri());
+        match Source::from_url(&url, None, 10).await {
+            Err(ImageDownloadError::TooLarge { size, max_bytes }) => {
+                assert_eq!(size, png.len());
+                assert_eq!(max_bytes, 10);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        let url = format!("{}/missing.png", server.uri());
+        assert!(matches!(
+            Source::from_url(&url, None, DEFAULT_MAX_IMAGE_BYTES).await,
+            Err(ImageDownloadError::Status(404))
+        ));
+    }
+    #[tokio::test]
+    async fn test_with_client() {
+        use wiremock::{
+            matchers::{header, method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages/count_tokens"))
+            .and(header("x-api-key", "test-key"))
+            .and(header("anthropic-version", "2023-06-01"))
+            .and(header("user-agent", "corporate-proxy-client"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"input_tokens": 3})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let http = reqwest::Client::builder()
+            .user_agent("corporate-proxy-client")
+            .build()
+            .unwrap();
+        let config = Config::new("test-key".to_string(), server.uri());
+        let client = AnthropicClient::with_client(config, http);
+        let body =
+            RequestBodyAnthropic::from_messages("claude-sonnet-4-20250514", 16, ["Hi"], None);
+        let tokens = client.count_tokens(&body).await.unwrap();
+        assert_eq!(tokens.input_tokens, 3);
+    }
+    #[test]
+    fn test_media_type_detection() {
+        assert_eq!(
+            MediaType::from_bytes(&[0xFF, 0xD8, 0xFF, 0xDB, 0x00]),
+            Some(MediaType::Jpeg)
+        );
+        assert_eq!(
+            MediaType::from_bytes(b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR"),
+            Some(MediaType::Png)
+        );
+        assert_eq!(
+            MediaType::from_bytes(b"GIF87a\x01\x00"),
+            Some(MediaType::Gif)
+        );
+        assert_eq!(
+            MediaType::from_bytes(b"RIFF\x1a\x00\x00\x00WEBPVP8L"),
+            Some(MediaType::Webp)
+        );
+        // Other RIFF containers and truncated headers are not WebP
+        assert_eq!(MediaType::from_bytes(b"RIFF\x24\x00\x00\x00WAVEfmt "), None);
+        assert_eq!(MediaType::from_bytes(b"RIFF\x1a\x00"), None);
+        assert_eq!(MediaType::from_bytes(b"BM\x36\x00\x0c\x00\x00\x00"), None);
+        assert_eq!(MediaType::from_bytes(&[0x13, 0x37, 0xC0, 0xDE, 0x42]), None);
+        assert_eq!(MediaType::from_bytes(&[]), None);
+
+        assert_eq!(MediaType::from_extension(".jpeg"), Some(MediaType::Jpeg));
+        assert_eq!(MediaType::from_extension("WebP"), Some(MediaType::Webp));
+        assert_eq!(MediaType::from_path("photos/cat.PNG"), Some(MediaType::Png));
+        assert_eq!(MediaType::from_path("image.bmp"), None);
+        assert_eq!(MediaType::from_path("README"), None);
+    }
+    #[test]
+    fn test_validate_image_size() {
+        let png = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/pixel.png"
+        ))
+        .unwrap();
+        let small = ContentType::new_image_from_bytes(&png, MediaType::Png).unwrap();
+        let oversize = ContentType::new_image(Source::new(
+            "A".repeat(DEFAULT_MAX_IMAGE_BYTES / 3 * 4 + 8),
+            MediaType::Png,
+        ));
+        let body = RequestBodyAnthropic::new(
+            "claude-sonnet-4-20250514",
+            1024,
+            vec![Messages::new(
+                Role::User,
+                MessageContent::ContentArray(vec![small.clone()]),
+            )],
+            None,
+        );
+        assert_eq!(body.validate(), Ok(()));
+
+        let body = RequestBodyAnthropic::new(
+            "claude-sonnet-4-20250514",
+            1024,
+            vec![Messages::new(
+                Role::User,
+                MessageContent::ContentArray(vec![
+                    ContentType::new_text("Compare these".to_string()),
+                    small,
+                    oversize,
+                ]),
+            )],
+            None,
+        );
+        let err = body.validate().unwrap_err();
+        assert_eq!(
+            err,
+            ValidationError::InvalidContentBlock {
+                message: 0,
+                block: 2,
+                reason: "image of 5242884 bytes exceeds the limit of 5242880 bytes".to_string(),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Invalid content block 2 of message 0: image of 5242884 bytes exceeds the limit of 5242880 bytes"
+        );
+        assert_eq!(
+            Source::new_url("https://example.com/cat.png".to_string()).validate(),
+            Ok(())
+        );
+    }
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_validate_image_dimensions() {
+        let mut png = std::io::Cursor::new(vec![]);
+        image::GrayImage::new(9000, 1)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let png = png.into_inner();
+        let source = Source::from_bytes(&png, MediaType::Png).unwrap();
+        match source.validate() {
+            Err(ValidationError::InvalidValue { value, .. }) => assert_eq!(value, "9000x1 pixels"),
+            other => panic!("unexpected result {:?}", other),
+        }
+        let pixel = Source::from_file(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/pixel.png"
+        ))
+        .unwrap();
+        assert_eq!(pixel.validate(), Ok(()));
+    }
+    #[tokio::test]
+    async fn test_api_url_with_prefix() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/proxy/anthropic/v1/messages/count_tokens"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"input_tokens": 3})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/proxy/anthropic/v1/models/claude-sonnet-4-20250514"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "claude-sonnet-4-20250514",
+                "type": "model",
+                "display_name": "Claude Sonnet 4",
+                "created_at": "2025-05-22T00:00:00Z"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api_url = format!("{}/proxy/anthropic/", server.uri());
+        let client = AnthropicClient::new(Config::new("test-key".to_string(), api_url)).unwrap();
+        let body =
+            RequestBodyAnthropic::from_messages("claude-sonnet-4-20250514", 16, ["Hi"], None);
+        assert_eq!(client.count_tokens(&body).await.unwrap().input_tokens, 3);
+        let model = client
+            .get_model_by_id("claude-sonnet-4-20250514".to_string())
+            .await
+            .unwrap();
+        assert_eq!(model.id, "claude-sonnet-4-20250514");
+    }
+    #[cfg(feature = "image-resize")]
+    #[test]
+    fn test_source_from_bytes_resized() {
+        fn encode(image: image::DynamicImage, format: image::ImageFormat) -> Vec<u8> {
+            let mut bytes = std::io::Cursor::new(vec![]);
+            image.write_to(&mut bytes, format).unwrap();
+            bytes.into_inner()
+        }
+        fn decode(source: &Source) -> image::DynamicImage {
+            match source {
+                Source::Base64 { data, .. } => {
+                    image::load_from_memory(&STANDARD.decode(data).unwrap()).unwrap()
+                }
+                other => panic!("unexpected source {:?}", other),
+            }
+        }
+
+        let png = encode(
+            image::DynamicImage::new_rgba8(3000, 1500),
+            image::ImageFormat::Png,
+        );
+        let (source, dimensions) = Source::from_bytes_resized(&png, MediaType::Png, 1568).unwrap();
+        assert_eq!(dimensions, (1568, 784));
+        assert!(matches!(
+            &source,
+            Source::Base64 {
+                media_type: MediaType::Png,
+                ..
+            }
+        ));
+        let resized = decode(&source);
+        assert_eq!((resized.width(), resized.height()), (1568, 784));
+
+        let jpeg = encode(
+            image::DynamicImage::new_rgb8(1000, 4000),
+            image::ImageFormat::Jpeg,
+        );
+        let (source, dimensions) = Source::from_bytes_resized(&jpeg, MediaType::Jpeg, 800).unwrap();
+        assert_eq!(dimensions, (200, 800));
+        assert!(matches!(
+            &source,
+            Source::Base64 {
+                media_type: MediaType::Jpeg,
+                ..
+            }
+        ));
+        let resized = decode(&source);
+        assert_eq!((resized.width(), resized.height()), (200, 800));
+
+        // Images within bounds are not re-encoded
+        let small = encode(
+            image::DynamicImage::new_rgba8(100, 50),
+            image::ImageFormat::Png,
+        );
+        let (source, dimensions) =
+            Source::from_bytes_resized(&small, MediaType::Png, RECOMMENDED_MAX_IMAGE_EDGE).unwrap();
+        assert_eq!(dimensions, (100, 50));
+        assert_eq!(source, Source::from_bytes(&small, MediaType::Png).unwrap());
+
+        assert!(Source::from_bytes_resized(&small, MediaType::Png, 0).is_err());
+        assert!(Source::from_bytes_resized(b"not an image", MediaType::Png, 100).is_err());
+    }
+    #[test]
+    fn test_response_unknown_fields() {
+        let res: ResponseBodyAnthropic = serde_json::from_value(serde_json::json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-20250514",
+            "content": [{"type": "text", "text": "Hi"}],
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "future_field": {"id": "future_1", "enabled": true},
+            "container": {"id": "container_1", "skills": []},
+            "usage": {
+                "input_tokens": 10,
+                "output_tokens": 2,
+                "future_usage": {"requests": 0},
+                "server_tool_use": {"web_search_requests": 1, "web_fetch_requests": 2}
+            }
+        }))
+        .unwrap();
+        assert_eq!(res.text(), "Hi");
+        assert_eq!(res.extra["future_field"]["id"], "future_1");
+        assert_eq!(res.usage.extra["future_usage"]["requests"], 0);
+        assert!(!res.extra.contains_key("usage"));
+        let container = res.container.as_ref().unwrap();
+        assert_eq!(container.expires_at, None);
+        assert_eq!(container.extra["skills"], serde_json::json!([]));
+        let server_tool_use = res.usage.server_tool_use.as_ref().unwrap();
+        assert_eq!(server_tool_use.web_search_requests, 1);
+        assert_eq!(server_tool_use.extra["web_fetch_requests"], 2);
+
+        // Unknown fields are written back unchanged
+        let value = serde_json::to_value(&res).unwrap();
+        assert_eq!(value["future_field"]["enabled"], true);
+        assert_eq!(value["usage"]["future_usage"]["requests"], 0);
+        assert_eq!(
+            value["container"],
+            serde_json::json!({"id": "container_1", "skills": []})
+        );
+        assert_eq!(
+            value["usage"]["server_tool_use"],
+            serde_json::json!({"web_search_requests": 1, "web_fetch_requests": 2})
+        );
+    }
+    #[test]
+    fn test_source_base64_validation() {
+        let png = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/pixel.png"
+        ))
+        .unwrap();
+        let encoded = STANDARD.encode(&png);
+        assert_eq!(
+            Source::new(encoded.clone(), MediaType::Png).validate(),
+            Ok(())
+        );
+
+        // Raw bytes passed as a string instead of base64
+        let raw = String::from_utf8_lossy(&png).to_string();
+        match Source::new(raw, MediaType::Png).validate() {
+            Err(ValidationError::InvalidValue { field, reason, .. }) => {
+                assert_eq!(field, "source.data");
+                assert!(reason.contains("Source::from_bytes"), "{}", reason);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        let uri = format!("data:image/png;base64,{}", encoded);
+        match Source::new(uri.clone(), MediaType::Png).validate() {
+            Err(ValidationError::InvalidValue { reason, .. }) => {
+                assert!(reason.contains("Source::from_data_uri"), "{}", reason)
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        assert_eq!(
+            Source::from_data_uri(&uri),
+            Ok(Source::new(encoded.clone(), MediaType::Png))
+        );
+        let uri = format!("data:image/webp;base64,{}", encoded);
+        assert!(matches!(
+            Source::from_data_uri(&uri),
+            Ok(Source::Base64 {
+                media_type: MediaType::Webp,
+                ..
+            })
+        ));
+        let uri = format!("data:image/bmp;base64,{}", encoded);
+        assert_eq!(
+            Source::from_data_uri(&uri),
+            Err(ValidationError::invalid_value(
+                "source.media_type",
+                "image/bmp",
+                "must be image/jpeg, image/png, image/gif or image/webp"
+            ))
+        );
+        assert!(matches!(
+            Source::from_data_uri(&encoded),
+            Err(ValidationError::InvalidValue {
+                field: "source",
+                ..
+            })
+        ));
+        assert!(Source::from_data_uri("data:image/png,not base64").is_err());
+        assert!(Source::from_data_uri("data:image/png;base64,not base64!").is_err());
+    }
+    #[test]
+    fn test_assistant_prefill() {
+        let prefill = Messages::assistant_prefill("Here is the JSON:\n{ \n\t".to_string());
+        assert_eq!(prefill.role, Role::Assistant);
+        assert_eq!(
+            prefill.content,
+            MessageContent::String("Here is the JSON:\n{".to_string())
+        );
+
+        let body = RequestBodyAnthropic::new(
+            "claude-sonnet-4-20250514",
+            1024,
+            vec![
+                Messages::new_user_message_prompt("List three colors as JSON".to_string()),
+                prefill,
+            ],
+            None,
+        );
+        assert_eq!(body.validate(), Ok(()));
+
+        let body = RequestBodyAnthropic::new(
+            "claude-sonnet-4-20250514",
+            1024,
+            vec![
+                Messages::new_user_message_prompt("List three colors as JSON".to_string()),
+                Messages::new_assistant_message_prompt("{ ".to_string()),
+            ],
+            None,
+        );
+        assert!(matches!(
+            body.validate(),
+            Err(ValidationError::InvalidMessage { index: 1, .. })
+        ));
+    }
+    #[test]
+    fn test_message_content_push() {
+        let source = Source::new_url("https://example.com/cat.png".to_string());
+        let mut content = MessageContent::new("What is in this image?");
+        assert_eq!(content.len(), 1);
+        content.push_image(source.clone());
+        assert_eq!(
+            content,
+            MessageContent::ContentArray(vec![
+                ContentType::new_text("What is in this image?".to_string()),
+                ContentType::new_image(source.clone()),
+            ])
+        );
+        content.push_text("Answer in one word");
+        assert_eq!(content.len(), 3);
+        assert!(matches!(
+            &content,
+            MessageContent::ContentArray(blocks)
+                if blocks[2] == ContentType::new_text("Answer in one word".to_string())
+        ));
+
+        // An empty string does not leave an empty text block behind
+        let mut content = MessageContent::default();
+        assert_eq!(content.len(), 0);
+        assert!(content.is_empty());
+        content.push(ContentType::new_image(source.clone()));
+        assert_eq!(
+            content,
+            MessageContent::ContentArray(vec![ContentType::new_image(source)])
+        );
+        assert!(!content.is_empty());
+
+        // An empty text block is still a block, validation rejects it
+        let content = MessageContent::new_content_array_text(vec!["".to_string()]);
+        assert_eq!(content.len(), 1);
+        assert!(!content.is_empty());
+    }
+    #[tokio::test]
+    async fn test_get_message_completed_raw() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(test_support::message_response())
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"unexpected": true})),
+            )
+            .mount(&server)
+            .await;
+
+        let client =
+            AnthropicClient::new(Config::new("test-key".to_string(), server.uri())).unwrap();
+        let body =
+            RequestBodyAnthropic::from_messages("claude-sonnet-4-20250514", 16, ["Hi"], None);
+        let (message, raw) = client.get_message_completed_raw(&body).await.unwrap();
+        assert_eq!(message.text(), "Hi");
+        assert_eq!(raw["id"], "msg_1");
+        assert_eq!(
+            serde_json::from_value::<ResponseBodyAnthropic>(raw).unwrap(),
+            message
+        );
+
+        // The JSON is still returned when it doesn't match the typed response
+        let raw = client.get_message_completed_json(&body).await.unwrap();
+        assert_eq!(raw, serde_json::json!({"unexpected": true}));
+        assert!(matches!(
+            client.get_message_completed_raw(&body).await,
+            Err(AnthropicError::Serialization(_))
+        ));
+    }
+    #[test]
+    fn test_message_content_conversions() {
+        let content: MessageContent = "hello".into();
+        assert_eq!(content, MessageContent::String("hello".to_string()));
+        let content: MessageContent = String::from("hello").into();
+        assert_eq!(serde_json::to_value(&content).unwrap(), "hello");
+        let content: MessageContent = vec![ContentType::new_text("hello".to_string())].into();
+        assert_eq!(
+            serde_json::to_value(&content).unwrap(),
+            serde_json::json!([{"type": "text", "text": "hello"}])
+        );
+
+        let message = Messages::new(Role::User, "hello");
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::json!({"role": "user", "content": "hello"})
+        );
+        let message: Messages = (Role::Assistant, "hi").into();
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::json!({"role": "assistant", "content": "hi"})
+        );
+        let message: Messages = (
+            Role::User,
+            vec![ContentType::new_image(Source::new_url(
+                "https://example.com/cat.png".to_string(),
+            ))],
+        )
+            .into();
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::json!({
+                "role": "user",
+                "content": [{
+                    "type": "image",
+                    "source": {"type": "url", "url": "https://example.com/cat.png"}
+                }]
+            })
+        );
+
+        let body = RequestBodyAnthropic::default().messages(vec![
+            (Role::User, "hi").into(),
+            (Role::Assistant, "Hello!".to_string()).into(),
+            Messages::new(Role::User, "How are you?"),
+        ]);
+        assert_eq!(body.messages.len(), 3);
+        assert_eq!(body.messages[1].role, Role::Assistant);
+        let result = Messages::new_tool_result_message("toolu_1".to_string(), "15 degrees", None);
+        assert!(matches!(
+            &result.content,
+            MessageContent::ContentArray(blocks) if matches!(
+                &blocks[0],
+                ContentType::ToolResult(ContentToolResult { content: Some(MessageContent::String(text)), .. })
+                    if text == "15 degrees"
+            )
+        ));
+    }
+    #[test]
+    fn test_message_content_as_text() {
+        let content = MessageContent::new("Hello\nworld");
+        assert_eq!(content.as_text(), "Hello\nworld");
+        assert_eq!(content.to_string(), "Hello\nworld");
+
+        let image =
+            ContentType::new_image(Source::new_url("https://example.com/cat.png".to_string()));
+        let content = MessageContent::ContentArray(vec![
+            ContentType::new_text("What is in this image?".to_string()),
+            image.clone(),
+            ContentType::new_tool_result("toolu_1".to_string(), "ignored", None),
+            ContentType::new_text("Answer briefly".to_string()),
+        ]);
+        assert_eq!(content.as_text(), "What is in this image?\nAnswer briefly");
+        assert_eq!(content.to_string(), content.as_text());
+        assert_eq!(image.text(), None);
+        assert_eq!(ContentType::new_text("Hi".to_string()).text(), Some("Hi"));
+
+        let content = MessageContent::ContentArray(vec![image]);
+        assert_eq!(content.as_text(), "");
+    }
+    #[tokio::test]
+    async fn test_quick_prompt() {
+        use wiremock::{
+            matchers::{body_partial_json, method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(body_partial_json(serde_json::json!({
+                "model": "claude-sonnet-4-20250514",
+                "max_tokens": QUICK_PROMPT_MAX_TOKENS,
+                "messages": [{"role": "user", "content": "What is the capital of France?"}]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "msg_1",
+                "type": "message",
+                "role": "assistant",
+                "model": "claude-sonnet-4-20250514",
+                "content": [
+                    {"type": "text", "text": "The capital of France "},
+                    {"type": "text", "text": "is Paris."}
+                ],
+                "stop_reason": "end_turn",
+                "stop_sequence": null,
+                "usage": {"input_tokens": 14, "output_tokens": 8}
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client =
+            AnthropicClient::new(Config::new("test-key".to_string(), server.uri())).unwrap();
+        let answer = client
+            .quick_prompt(ClaudeModel::ClaudeSonnet4, "What is the capital of France?")
+            .await
+            .unwrap();
+        assert_eq!(answer, "The capital of France is Paris.");
+    }
+    #[test]
+    fn test_unified_content_model() {
+        let fixture = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/tool_use_response.json"
+        ));
+        let res: ResponseBodyAnthropic = serde_json::from_str(fixture).unwrap();
+        let tool_use_id = res.tool_uses()[0].id.clone();
+
+        let body = RequestBodyAnthropic::new(
+            ClaudeModel::ClaudeSonnet4,
+            1024,
+            vec![
+                Messages::from("What is the weather in Paris?"),
+                Messages::from(res),
+                Messages::new_tool_result_message(tool_use_id, "15 degrees", None),
+            ],
+            None,
+        );
+        assert_eq!(body.validate(), Ok(()));
+        assert_eq!(body.messages[1].role, Role::Assistant);
+        let fixture: serde_json::Value = serde_json::from_str(fixture).unwrap();
+        let json = serde_json::to_value(&body).unwrap();
+        assert_eq!(json["messages"][1]["content"], fixture["content"]);
+    }
+    #[test]
+    fn test_unknown_content_block() {
+        let container_upload = serde_json::json!({
+            "type": "container_upload",
+            "file_id": "file_1",
+            "details": {"size": 12}
+        });
+        let res: ResponseBodyAnthropic = serde_json::from_value(serde_json::json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-20250514",
+            "content": [
+                {"type": "text", "text": "I uploaded the file. "},
+                container_upload,
+                {"type": "text", "text": "It is ready."}
+            ],
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 10, "output_tokens": 20}
+        }))
+        .unwrap();
+        assert_eq!(res.text(), "I uploaded the file. It is ready.");
+        match &res.content[1] {
+            ContentType::Unknown(value) => {
+                assert_eq!(value, &container_upload);
+                assert_eq!(value["details"]["size"], 12);
+            }
+            other => panic!("unexpected block {:?}", other),
+        }
+        // Sent back unchanged with the assistant turn
+        let json = serde_json::to_value(Messages::from(res)).unwrap();
+        assert_eq!(json["content"][1], container_upload);
+        assert_eq!(json["content"][0]["type"], "text");
+    }
+    #[test]
+    fn test_web_search_response_usage() {
+        let fixture = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/web_search_response.json"
+        ));
+        let res: ResponseBodyAnthropic = serde_json::from_str(fixture).unwrap();
+        assert_eq!(
+            res.usage.server_tool_use,
+            Some(ServerToolUsage {
+                web_search_requests: 1,
+                extra: HashMap::new(),
+            })
+        );
+        assert!(res.usage.extra.is_empty());
+        assert!(res.extra.is_empty());
+        assert_eq!(res.container, None);
+        assert!(matches!(&res.content[1], ContentType::ServerToolUse(_)));
+        assert!(matches!(
+            &res.content[2],
+            ContentType::WebSearchToolResult(_)
+        ));
+
+        let mut json: serde_json::Value = serde_json::from_str(fixture).unwrap();
+        json["container"] = serde_json::json!({
+            "id": "container_011CPR5CNjB747bTd36fQLFk",
+            "expires_at": "2025-05-23T21:13:31.749448Z"
+        });
+        let res: ResponseBodyAnthropic = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            res.container,
+            Some(Container {
+                id: "container_011CPR5CNjB747bTd36fQLFk".to_string(),
+                expires_at: Some("2025-05-23T21:13:31.749448Z".to_string()),
+                extra: HashMap::new(),
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&res).unwrap()["container"],
+            json["container"]
+        );
+    }
+    #[test]
+    fn test_media_type_mime() {
+        for media_type in [
+            MediaType::Jpeg,
+            MediaType::Png,
+            MediaType::Gif,
+            MediaType::Webp,
+        ] {
+            assert_eq!(
+                MediaType::from_mime(media_type.to_mime()),
+                Some(media_type.clone())
+            );
+            assert_eq!(
+                serde_json::to_value(&media_type).unwrap(),
+                media_type.to_mime()
+            );
+        }
+        assert_eq!(MediaType::from_mime("IMAGE/JPG"), Some(MediaType::Jpeg));
+        assert_eq!(
+            MediaType::from_mime("image/png; charset=binary"),
+            Some(MediaType::Png)
+        );
+        assert_eq!(MediaType::from_mime("image/bmp"), None);
+        assert_eq!(MediaType::from_mime(""), None);
+        // Documents are not images, see DocumentMediaType
+        assert_eq!(MediaType::from_mime("application/pdf"), None);
+        assert_eq!(MediaType::from_mime("text/plain"), None);
+    }
+    #[tokio::test]
+    async fn test_config_validate() {
+        use wiremock::{
+            matchers::{body_partial_json, method, path},
+            Mock, MockServer,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(body_partial_json(serde_json::json!({"top_k": 0})))
+            .respond_with(test_support::message_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+        let body = test_support::body().with_top_k(0);
+
+        // The invalid body never reaches the server
+        let client =
+            AnthropicClient::new(Config::new("test-key".to_string(), server.uri())).unwrap();
+        assert!(matches!(
+            client.get_message_completed(&body).await,
+            Err(AnthropicError::Validation(_))
+        ));
+
+        let mut config = Config::new("test-key".to_string(), server.uri());
+        config.set_validate(false);
+        let client = AnthropicClient::new(config).unwrap();
+        assert_eq!(
+            client.get_message_completed(&body).await.unwrap().text(),
+            "Hi"
+        );
+    }
 }
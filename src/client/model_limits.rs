@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+/// Known maximum output tokens per model family
+/// Model ids are matched by prefix so dated snapshots and `-latest` aliases share a limit
+const KNOWN_OUTPUT_LIMITS: &[(&str, u32)] = &[
+    ("claude-3-haiku", 4096),
+    ("claude-3-sonnet", 4096),
+    ("claude-3-opus", 4096),
+    ("claude-3-5-haiku", 8192),
+    ("claude-3-5-sonnet", 8192),
+    ("claude-3-7-sonnet", 64000),
+    ("claude-sonnet-4", 64000),
+    ("claude-haiku-4", 64000),
+    ("claude-opus-4", 32000),
+];
+
+/// Table of maximum output tokens per model, used to validate `max_tokens` before sending
+/// Defaults to the limits of the known Claude models, entries can be added or overridden for
+/// models released after this crate
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelLimits {
+    limits: HashMap<String, u32>,
+}
+impl Default for ModelLimits {
+    fn default() -> Self {
+        Self {
+            limits: KNOWN_OUTPUT_LIMITS
+                .iter()
+                .map(|(model, limit)| (model.to_string(), *limit))
+                .collect(),
+        }
+    }
+}
+impl ModelLimits {
+    /// Create an empty table without any known limits
+    pub fn empty() -> Self {
+        Self {
+            limits: HashMap::new(),
+        }
+    }
+    /// Set the maximum output tokens for a model id or model id prefix
+    pub fn with_limit(mut self, model: impl Into<String>, max_output_tokens: u32) -> Self {
+        self.limits.insert(model.into(), max_output_tokens);
+        self
+    }
+    /// Returns the maximum output tokens of a model, using the longest matching prefix
+    /// Returns None for unknown models
+    pub fn max_output_tokens(&self, model: &str) -> Option<u32> {
+        self.limits
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, limit)| *limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_limits() {
+        let limits = ModelLimits::default();
+        assert_eq!(
+            limits.max_output_tokens("claude-3-5-sonnet-20241022"),
+            Some(8192)
+        );
+        assert_eq!(limits.max_output_tokens("claude-3-opus-latest"), Some(4096));
+        assert_eq!(
+            limits.max_output_tokens("claude-sonnet-4-20250514"),
+            Some(64000)
+        );
+        assert_eq!(limits.max_output_tokens("my-custom-model"), None);
+    }
+
+    #[test]
+    fn test_override_limits() {
+        let limits = ModelLimits::default()
+            .with_limit("my-custom-model", 1024)
+            .with_limit("claude-3-5-sonnet-20241022", 4096);
+        assert_eq!(limits.max_output_tokens("my-custom-model"), Some(1024));
+        assert_eq!(
+            limits.max_output_tokens("claude-3-5-sonnet-20241022"),
+            Some(4096)
+        );
+        assert_eq!(
+            limits.max_output_tokens("claude-3-5-sonnet-latest"),
+            Some(8192)
+        );
+        assert_eq!(
+            ModelLimits::empty().max_output_tokens("claude-3-5-sonnet-20241022"),
+            None
+        );
+    }
+}
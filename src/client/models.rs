@@ -1,53 +1,44 @@
-use reqwest::StatusCode;
+use core::fmt;
+use std::collections::{HashMap, VecDeque};
+
+use futures::{stream, Stream};
 use serde::{Deserialize, Serialize};
 
-use super::AnthropicClient;
+use super::{
+    error::{parse_response, AnthropicError},
+    options::RequestOptions,
+    AnthropicClient,
+};
 
 /// Client implementation for interacting with Anthropic's model API endpoints.
 impl AnthropicClient {
     /// Retrieves a list of all available models from the Anthropic API.
     ///
     /// # Returns
-    /// * `Result<GetModelsBody, anyhow::Error>` - A Result containing either:
+    /// * `Result<GetModelsBody, AnthropicError>` - A Result containing either:
     ///   * `GetModelsBody` - The successful response containing model information
-    ///   * `anyhow::Error` - Any error that occurred during the request
+    ///   * `AnthropicError` - Any error that occurred during the request
     ///
     /// # Errors
     /// Returns an error if:
     /// * The HTTP request fails
-    /// * The response status is not 200
+    /// * The response status is not 200 after retrying 429, 529 and 5xx statuses
     /// * The response body cannot be parsed
-
-    /// Retrieves a filtered list of models based on the provided query parameters.
-    ///
-    /// # Arguments
-    /// * `params` - Query parameters to filter the models
-    ///
-    /// # Returns
-    /// * `Result<GetModelsBody, anyhow::Error>` - A Result containing either:
-    ///   * `GetModelsBody` - The successful response containing filtered model information
-    ///   * `anyhow::Error` - Any error that occurred during the request
-    ///
-    /// # Errors
-    /// Returns an error if:
-    /// * The query parameters cannot be serialized
-    /// * The HTTP request fails
-    /// * The response status is not 200
-    /// * The response body cannot be parsed
-    pub async fn get_models(&self) -> Result<GetModelsBody, anyhow::Error> {
-        let url = format!("{}/v1/models", self.api_url);
-        let response = self
-            .client
-            .get(&url)
-            .header("anthropic-version", "2023-06-01")
-            .header("x-api-key", &self.api_key)
-            .send()
-            .await?;
-        if response.status() != 200 {
-            return Err(anyhow::anyhow!(response.text().await?));
-        }
-        let body: GetModelsBody = response.json().await?;
-        Ok(body)
+    pub async fn get_models(&self) -> Result<GetModelsBody, AnthropicError> {
+        self.get_models_with_options(&RequestOptions::default())
+            .await
+    }
+    /// Same as `get_models` with per-request options such as beta headers or the version
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(url, status))
+    )]
+    pub async fn get_models_with_options(
+        &self,
+        options: &RequestOptions,
+    ) -> Result<GetModelsBody, AnthropicError> {
+        let response = self.send_with_retry(|| self.get("models", options)).await?;
+        parse_response(response).await
     }
 
     /// Retrieves model information from the Anthropic API with specified query parameters
@@ -60,63 +51,134 @@ impl AnthropicClient {
     ///
     /// Returns a [`Result`] containing [`GetModelsBody`] on success, or an error if:
     /// - The API request fails
-    /// - Response status is not 200
+    /// - Response status is not 200 after retrying 429, 529 and 5xx statuses
     /// - Response body cannot be parsed
-    ///
-
     pub async fn get_model_with_params(
         &self,
         params: GetModelsQueryParams,
-    ) -> Result<GetModelsBody, anyhow::Error> {
-        let url = format!("{}/v1/models", self.api_url);
-        let response = self
-            .client
-            .get(&url)
-            .header("anthropic-version", "2023-06-01")
-            .header("x-api-key", &self.api_key)
-            .query(&params)
-            .send()
-            .await?;
-        println!("Test");
-        println!("{:#?}", response.url());
-        if response.status() != StatusCode::OK {
-            return Err(anyhow::anyhow!(response.text().await?));
-        }
-        let body: GetModelsBody = response.json().await?;
-        Ok(body)
+    ) -> Result<GetModelsBody, AnthropicError> {
+        self.get_model_with_params_with_options(params, &RequestOptions::default())
+            .await
     }
-    pub async fn get_model_by_id(&self, model_id: String) -> Result<Model, anyhow::Error> {
-        let url = format!("{}/v1/models/{}", self.api_url, model_id);
+    /// Same as `get_model_with_params` with per-request options such as beta headers or the version
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(url, status))
+    )]
+    pub async fn get_model_with_params_with_options(
+        &self,
+        params: GetModelsQueryParams,
+        options: &RequestOptions,
+    ) -> Result<GetModelsBody, AnthropicError> {
         let response = self
-            .client
-            .get(&url)
-            .header("anthropic-version", "2023-06-01")
-            .header("x-api-key", &self.api_key)
-            .send()
+            .send_with_retry(|| self.get("models", options).query(&params))
             .await?;
-        if response.status() != StatusCode::OK {
-            return Err(anyhow::anyhow!(response.text().await?));
+        parse_response(response).await
+    }
+    pub async fn get_model_by_id(&self, model_id: String) -> Result<Model, AnthropicError> {
+        self.get_model_by_id_with_options(model_id, &RequestOptions::default())
+            .await
+    }
+    /// Same as `get_model_by_id` with per-request options such as beta headers or the version
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(url, status))
+    )]
+    pub async fn get_model_by_id_with_options(
+        &self,
+        model_id: String,
+        options: &RequestOptions,
+    ) -> Result<Model, AnthropicError> {
+        let path = format!("models/{}", model_id);
+        let response = self.send_with_retry(|| self.get(&path, options)).await?;
+        parse_response(response).await
+    }
+
+    /// Lists every available model, following the pagination of [`get_models`](Self::get_models).
+    ///
+    /// # Returns
+    /// A [`Stream`] yielding the models newest first. Pages are requested as the stream is read,
+    /// using the `last_id` of the previous page until `has_more` is false.
+    ///
+    /// # Errors
+    /// Items of the stream are errors if a page cannot be fetched, the stream ends after the error.
+    pub fn list_all_models(&self) -> impl Stream<Item = Result<Model, AnthropicError>> + '_ {
+        let state = (Some(GetModelsQueryParams::default()), VecDeque::new());
+        stream::unfold(state, move |(mut params, mut pending)| async move {
+            loop {
+                if let Some(model) = pending.pop_front() {
+                    return Some((Ok(model), (params, pending)));
+                }
+                match self.get_model_with_params(params.take()?).await {
+                    Ok(page) => {
+                        if page.has_more {
+                            params = page.last_id.map(|last_id| {
+                                GetModelsQueryParams::default().with_after_id(last_id)
+                            });
+                        }
+                        pending.extend(page.data);
+                    }
+                    Err(e) => return Some((Err(e), (None, pending))),
+                }
+            }
+        })
+    }
+
+    /// Resolves a model alias such as `claude-3-5-sonnet-latest` to the dated model it points to.
+    ///
+    /// # Arguments
+    /// * `alias` - The alias or model id to resolve
+    ///
+    /// # Returns
+    /// The [`Model`] with its dated `id`. The model is looked up directly first, if that returns 404
+    /// the pages of [`get_models`](Self::get_models) are searched for the newest model matching the alias,
+    /// i.e. the alias with `latest` replaced by a date, so `claude-opus-4-latest` never resolves to `claude-opus-4-1`.
+    ///
+    /// # Errors
+    /// Returns an error if:
+    /// * The HTTP request fails
+    /// * No model matches the alias, as [`AnthropicError::Api`] with status 404
+    pub async fn resolve_model_alias(&self, alias: &str) -> Result<Model, AnthropicError> {
+        match self.get_model_by_id(alias.to_string()).await {
+            Err(AnthropicError::Api { status: 404, .. }) => {}
+            res => return res,
+        }
+        // Models are listed newest first so the first match is the one the alias points to
+        let mut params = GetModelsQueryParams::default();
+        loop {
+            let page = self.get_model_with_params(params).await?;
+            if let Some(model) = page
+                .data
+                .into_iter()
+                .find(|model| model.id == alias || is_dated_version_of(&model.id, alias))
+            {
+                return Ok(model);
+            }
+            if !page.has_more || page.last_id.is_none() {
+                return Err(AnthropicError::Api {
+                    status: 404,
+                    message: format!("No model found for alias {}", alias),
+                });
+            }
+            params = GetModelsQueryParams::new(None, page.last_id, None);
         }
-        let body: Model = response.json().await?;
-        Ok(body)
     }
 }
-#[derive(Debug, Serialize, Deserialize)]
-
+/// True if `id` is `alias` with its `latest` suffix replaced by a date, e.g.
+/// `claude-opus-4-20250514` for `claude-opus-4-latest` but not `claude-opus-4-1-20250805`
+fn is_dated_version_of(id: &str, alias: &str) -> bool {
+    let Some(family) = alias.strip_suffix("latest") else {
+        return false;
+    };
+    id.strip_prefix(family)
+        .is_some_and(|date| date.len() == 8 && date.bytes().all(|byte| byte.is_ascii_digit()))
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct GetModelsQueryParams {
     before_id: Option<String>,
     after_id: Option<String>,
     limit: Option<i32>,
 }
-impl Default for GetModelsQueryParams {
-    fn default() -> Self {
-        GetModelsQueryParams {
-            before_id: None,
-            after_id: None,
-            limit: None,
-        }
-    }
-}
 impl GetModelsQueryParams {
     pub fn new(before_id: Option<String>, after_id: Option<String>, limit: Option<i32>) -> Self {
         GetModelsQueryParams {
@@ -125,8 +187,23 @@ impl GetModelsQueryParams {
             limit,
         }
     }
+    /// Only list models before this model id
+    pub fn with_before_id(mut self, before_id: impl Into<String>) -> Self {
+        self.before_id = Some(before_id.into());
+        self
+    }
+    /// Only list models after this model id, the `last_id` of the previous page
+    pub fn with_after_id(mut self, after_id: impl Into<String>) -> Self {
+        self.after_id = Some(after_id.into());
+        self
+    }
+    /// Number of models per page, the API defaults to 20
+    pub fn with_limit(mut self, limit: i32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
 }
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 
 pub struct GetModelsBody {
     pub first_id: Option<String>,
@@ -134,24 +211,354 @@ pub struct GetModelsBody {
     pub has_more: bool,
     pub data: Vec<Model>,
 }
-#[derive(Debug, Serialize, Deserialize)]
+/// Model returned by the models endpoints
+/// extra: Fields added to the API after this version of the crate, kept as raw JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Model {
     pub id: String,
     pub display_name: String,
     #[serde(rename = "type")]
     pub model_type: ModelEnums,
     pub created_at: String,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
-#[derive(Debug, Serialize, Deserialize)]
-
+#[cfg(feature = "chrono")]
+impl Model {
+    /// Parses `created_at`, an RFC 3339 timestamp
+    pub fn created_at_datetime(&self) -> Result<chrono::DateTime<chrono::Utc>, chrono::ParseError> {
+        chrono::DateTime::parse_from_rfc3339(&self.created_at)
+            .map(|created_at| created_at.with_timezone(&chrono::Utc))
+    }
+}
+/// Type of a model object
+/// Unknown: A type not known to this crate yet, so new types do not break deserialization
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ModelEnums {
     #[serde(rename = "model")]
     Models,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Model ids known when this version of the crate was released
+/// Other: Any model id, e.g. a model released after the crate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClaudeModel {
+    Claude3Haiku,
+    Claude3Opus,
+    Claude35Haiku,
+    Claude35Sonnet,
+    Claude37Sonnet,
+    ClaudeSonnet4,
+    ClaudeOpus4,
+    ClaudeOpus41,
+    ClaudeSonnet45,
+    ClaudeHaiku45,
+    Other(String),
+}
+impl ClaudeModel {
+    /// The model id sent to the API
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Claude3Haiku => "claude-3-haiku-20240307",
+            Self::Claude3Opus => "claude-3-opus-20240229",
+            Self::Claude35Haiku => "claude-3-5-haiku-20241022",
+            Self::Claude35Sonnet => "claude-3-5-sonnet-20241022",
+            Self::Claude37Sonnet => "claude-3-7-sonnet-20250219",
+            Self::ClaudeSonnet4 => "claude-sonnet-4-20250514",
+            Self::ClaudeOpus4 => "claude-opus-4-20250514",
+            Self::ClaudeOpus41 => "claude-opus-4-1-20250805",
+            Self::ClaudeSonnet45 => "claude-sonnet-4-5-20250929",
+            Self::ClaudeHaiku45 => "claude-haiku-4-5-20251001",
+            Self::Other(model) => model,
+        }
+    }
+}
+impl fmt::Display for ClaudeModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+impl AsRef<str> for ClaudeModel {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+impl From<ClaudeModel> for String {
+    fn from(model: ClaudeModel) -> Self {
+        model.to_string()
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::client::{Config, Version};
+    use futures::StreamExt;
+    use wiremock::{
+        matchers::{header, method, path, query_param, query_param_is_missing},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn model(id: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "display_name": id,
+            "type": "model",
+            "created_at": "2024-10-22T00:00:00Z"
+        })
+    }
+
+    fn page(ids: &[&str], has_more: bool) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "data": ids.iter().map(|id| model(id)).collect::<Vec<_>>(),
+            "first_id": ids.first(),
+            "last_id": ids.last(),
+            "has_more": has_more
+        }))
+    }
+
+    fn client(server: &MockServer) -> AnthropicClient {
+        AnthropicClient::new(Config::new("test-key".to_string(), server.uri())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_resolve_model_alias_direct() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models/claude-3-5-sonnet-latest"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(model("claude-3-5-sonnet-20241022")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let model = client(&server)
+            .resolve_model_alias("claude-3-5-sonnet-latest")
+            .await
+            .unwrap();
+        assert_eq!(model.id, "claude-3-5-sonnet-20241022");
+    }
+    #[tokio::test]
+    async fn test_resolve_model_alias_from_list() {
+        let server = MockServer::start().await;
+        let not_found = serde_json::json!({
+            "type": "error",
+            "error": {"type": "not_found_error", "message": "model not found"}
+        });
+        Mock::given(method("GET"))
+            .and(path("/v1/models/claude-3-5-sonnet-latest"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(&not_found))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models/claude-2-latest"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(&not_found))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(query_param_is_missing("after_id"))
+            .respond_with(page(
+                &["claude-opus-4-20250514", "claude-3-7-sonnet-20250219"],
+                true,
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(query_param("after_id", "claude-3-7-sonnet-20250219"))
+            .respond_with(page(
+                &["claude-3-5-sonnet-20241022", "claude-3-5-sonnet-20240620"],
+                false,
+            ))
+            .mount(&server)
+            .await;
+
+        let client = client(&server);
+        let model = client
+            .resolve_model_alias("claude-3-5-sonnet-latest")
+            .await
+            .unwrap();
+        assert_eq!(model.id, "claude-3-5-sonnet-20241022");
+
+        let err = client
+            .resolve_model_alias("claude-2-latest")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, AnthropicError::Api { status: 404, ref message } if message.contains("claude-2-latest"))
+        );
+    }
+    #[tokio::test]
+    async fn test_resolve_model_alias_shared_prefix() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models/claude-opus-4-latest"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({
+                "type": "error",
+                "error": {"type": "not_found_error", "message": "model not found"}
+            })))
+            .mount(&server)
+            .await;
+        // The newer claude-opus-4-1 shares the prefix of claude-opus-4
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .respond_with(page(
+                &["claude-opus-4-1-20250805", "claude-opus-4-20250514"],
+                false,
+            ))
+            .mount(&server)
+            .await;
+
+        let model = client(&server)
+            .resolve_model_alias("claude-opus-4-latest")
+            .await
+            .unwrap();
+        assert_eq!(model.id, "claude-opus-4-20250514");
+        assert!(!is_dated_version_of(
+            "claude-3-5-sonnet-20241022",
+            "claude-3-latest"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_models_sends_client_headers() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(header("anthropic-version", "2024-10-22"))
+            .and(header("x-api-key", "test-key"))
+            .respond_with(page(&["claude-3-5-sonnet-20241022"], false))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let config = Config::new_with_version(
+            "test-key".to_string(),
+            server.uri(),
+            Version::Custom("2024-10-22".to_string()),
+        );
+        let client = AnthropicClient::new(config).unwrap();
+        let models = client.get_models().await.unwrap();
+        assert_eq!(models.data[0].id, "claude-3-5-sonnet-20241022");
+    }
+
+    #[tokio::test]
+    async fn test_get_models_with_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(query_param("limit", "1"))
+            .and(query_param_is_missing("before_id"))
+            .and(query_param_is_missing("after_id"))
+            .respond_with(page(&["claude-sonnet-4-20250514"], true))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let params = GetModelsQueryParams::default().with_limit(1);
+        let models = client(&server).get_model_with_params(params).await.unwrap();
+        assert_eq!(models.data.len(), 1);
+        assert!(models.has_more);
+    }
+
+    #[tokio::test]
+    async fn test_list_all_models() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(query_param_is_missing("after_id"))
+            .respond_with(page(
+                &["claude-opus-4-20250514", "claude-sonnet-4-20250514"],
+                true,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(query_param("after_id", "claude-sonnet-4-20250514"))
+            .respond_with(page(&["claude-3-7-sonnet-20250219"], false))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = client(&server);
+        let models: Vec<Model> = client
+            .list_all_models()
+            .map(|model| model.unwrap())
+            .collect()
+            .await;
+        let ids: Vec<&str> = models.iter().map(|model| model.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "claude-opus-4-20250514",
+                "claude-sonnet-4-20250514",
+                "claude-3-7-sonnet-20250219"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_model_type() {
+        let model: Model = serde_json::from_value(model("claude-sonnet-4-20250514")).unwrap();
+        assert_eq!(model.model_type, ModelEnums::Models);
+
+        let model: Model = serde_json::from_value(serde_json::json!({
+            "id": "claude-embed-1",
+            "display_name": "Claude Embed",
+            "type": "embedding_model",
+            "created_at": "2025-10-01T00:00:00Z",
+            "max_input_tokens": 32000
+        }))
+        .unwrap();
+        assert_eq!(model.model_type, ModelEnums::Unknown);
+        assert_eq!(model.id, "claude-embed-1");
+        assert_eq!(model.extra["max_input_tokens"], 32000);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_created_at_datetime() {
+        use chrono::{TimeZone, Utc};
+
+        let mut model: Model = serde_json::from_value(model("claude-3-5-sonnet-20241022")).unwrap();
+        assert_eq!(
+            model.created_at_datetime().unwrap(),
+            Utc.with_ymd_and_hms(2024, 10, 22, 0, 0, 0).unwrap()
+        );
+        model.created_at = "2025-02-24T18:00:00+02:00".to_string();
+        assert_eq!(
+            model.created_at_datetime().unwrap(),
+            Utc.with_ymd_and_hms(2025, 2, 24, 16, 0, 0).unwrap()
+        );
+        model.created_at = "yesterday".to_string();
+        assert!(model.created_at_datetime().is_err());
+    }
+
+    #[test]
+    fn test_claude_model_ids() {
+        for (model, id) in [
+            (ClaudeModel::Claude3Haiku, "claude-3-haiku-20240307"),
+            (ClaudeModel::Claude3Opus, "claude-3-opus-20240229"),
+            (ClaudeModel::Claude35Haiku, "claude-3-5-haiku-20241022"),
+            (ClaudeModel::Claude35Sonnet, "claude-3-5-sonnet-20241022"),
+            (ClaudeModel::Claude37Sonnet, "claude-3-7-sonnet-20250219"),
+            (ClaudeModel::ClaudeSonnet4, "claude-sonnet-4-20250514"),
+            (ClaudeModel::ClaudeOpus4, "claude-opus-4-20250514"),
+            (ClaudeModel::ClaudeOpus41, "claude-opus-4-1-20250805"),
+            (ClaudeModel::ClaudeSonnet45, "claude-sonnet-4-5-20250929"),
+            (ClaudeModel::ClaudeHaiku45, "claude-haiku-4-5-20251001"),
+            (ClaudeModel::Other("claude-next".to_string()), "claude-next"),
+        ] {
+            assert_eq!(model.to_string(), id);
+            assert_eq!(String::from(model), id);
+        }
+    }
 
     #[tokio::test]
     async fn test_get_models() {
@@ -187,4 +594,39 @@ pub mod tests {
             .unwrap();
         assert_eq!(models.id, "claude-3-5-sonnet-20241022");
     }
+
+    #[test]
+    fn test_claude_model_as_str() {
+        assert_eq!(
+            ClaudeModel::Claude35Sonnet.as_str(),
+            "claude-3-5-sonnet-20241022"
+        );
+        assert_eq!(
+            ClaudeModel::ClaudeSonnet4.as_str(),
+            "claude-sonnet-4-20250514"
+        );
+        assert_eq!(
+            ClaudeModel::Other("claude-next".to_string()).as_str(),
+            "claude-next"
+        );
+        assert_eq!(
+            ClaudeModel::ClaudeHaiku45.to_string(),
+            ClaudeModel::ClaudeHaiku45.as_str()
+        );
+
+        let body = crate::client::RequestBodyAnthropic::from_messages(
+            ClaudeModel::Claude35Haiku,
+            16,
+            ["Hi"],
+            None,
+        );
+        assert_eq!(body.model, "claude-3-5-haiku-20241022");
+        let body = crate::client::RequestBodyAnthropic::from_messages(
+            "claude-3-5-haiku-20241022",
+            16,
+            ["Hi"],
+            None,
+        );
+        assert_eq!(body.model, ClaudeModel::Claude35Haiku.as_str());
+    }
 }
@@ -0,0 +1,361 @@
+use reqwest::RequestBuilder;
+
+use super::{RequestBodyAnthropic, Version};
+
+const ANTHROPIC_BETA: &str = "anthropic-beta";
+const ANTHROPIC_VERSION: &str = "anthropic-version";
+/// Beta required to reference uploaded files with a file source, added automatically
+pub const FILES_API_BETA: &str = "files-api-2025-04-14";
+
+/// Options applied to a single request on top of the client configuration
+/// betas: Beta features to opt into, sent comma separated in the `anthropic-beta` header
+/// together with the beta features of the client config
+/// version: Overrides the `anthropic-version` of the client for this request
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RequestOptions {
+    pub betas: Vec<String>,
+    pub version: Option<Version>,
+}
+impl RequestOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Opt into a beta feature, e.g. `token-efficient-tools-2025-02-19`
+    pub fn with_beta(mut self, beta: impl Into<String>) -> Self {
+        self.betas.push(beta.into());
+        self
+    }
+    /// Send this request with a different `anthropic-version` than the client
+    pub fn with_version(mut self, version: Version) -> Self {
+        self.version = Some(version);
+        self
+    }
+    /// The options with the betas required by the content of `body` added
+    pub(crate) fn for_body(&self, body: &RequestBodyAnthropic) -> Self {
+        let mut options = self.clone();
+        if body.uses_files_api() {
+            options = options.with_beta(FILES_API_BETA);
+        }
+        options
+    }
+    /// Adds the headers of the options to a request
+    /// default_version: The version of the client, used unless the options override it
+    /// default_betas: The beta features of the client, sent before the betas of the options
+    pub(crate) fn apply(
+        &self,
+        request: RequestBuilder,
+        default_version: &Version,
+        default_betas: &[String],
+    ) -> RequestBuilder {
+        let version = self.version.as_ref().unwrap_or(default_version);
+        let request = request.header(ANTHROPIC_VERSION, version.to_string());
+        let mut betas: Vec<&str> = vec![];
+        for beta in default_betas.iter().chain(&self.betas) {
+            if !betas.contains(&beta.as_str()) {
+                betas.push(beta);
+            }
+        }
+        if betas.is_empty() {
+            return request;
+        }
+        request.header(ANTHROPIC_BETA, betas.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{
+        test_support::{body, message_response},
+        AnthropicClient, Config, ContentType, DocumentSource, MessageContent, Messages, Role,
+    };
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, Request, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_beta_header() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            // Both betas must arrive joined in a single header value
+            .and(|req: &Request| {
+                req.headers.get_all("anthropic-beta").iter().count() == 1
+                    && req.headers["anthropic-beta"]
+                        == "token-efficient-tools-2025-02-19,context-1m-2025-08-07"
+            })
+            .respond_with(message_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client =
+            AnthropicClient::new(Config::new("test-key".to_string(), server.uri())).unwrap();
+        let options = RequestOptions::new()
+            .with_beta("token-efficient-tools-2025-02-19")
+            .with_beta("context-1m-2025-08-07");
+        let res = client
+            .get_message_completed_with_options(&body(), &options)
+            .await
+            .unwrap();
+        assert_eq!(res.id, "msg_1");
+    }
+
+    #[tokio::test]
+    async fn test_version_header() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(header("anthropic-version", "2023-01-01"))
+            .respond_with(message_response())
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(header("anthropic-version", "2023-06-01"))
+            .respond_with(message_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut client =
+            AnthropicClient::new(Config::new("test-key".to_string(), server.uri())).unwrap();
+        // The client default is used when the options do not override it
+        client.get_message_completed(&body()).await.unwrap();
+        let options = RequestOptions::new().with_version(Version::Initial);
+        client
+            .get_message_completed_with_options(&body(), &options)
+            .await
+            .unwrap();
+        // set_version applies to requests sent after it
+        client.set_version(Version::Initial);
+        client.get_message_completed(&body()).await.unwrap();
+    }
+    #[tokio::test]
+    async fn test_custom_version_header() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(header("anthropic-version", "2024-10-22"))
+            .respond_with(message_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let config = Config::new_with_version(
+            "test-key".to_string(),
+            server.uri(),
+            Version::Custom("2024-10-22".to_string()),
+        );
+        let client = AnthropicClient::new(config).unwrap();
+        client.get_message_completed(&body()).await.unwrap();
+    }
+    #[tokio::test]
+    async fn test_config_beta_features() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(|req: &Request| {
+                req.headers["anthropic-beta"] == "pdfs-2024-09-25,output-128k-2025-02-19"
+            })
+            .respond_with(message_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(|req: &Request| {
+                req.headers["anthropic-beta"]
+                    == "pdfs-2024-09-25,output-128k-2025-02-19,token-efficient-tools-2025-02-19"
+            })
+            .respond_with(message_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut config = Config::new("test-key".to_string(), server.uri());
+        config.set_beta_features(vec![
+            "pdfs-2024-09-25".to_string(),
+            "output-128k-2025-02-19".to_string(),
+        ]);
+        let client = AnthropicClient::new(config).unwrap();
+        client.get_message_completed(&body()).await.unwrap();
+        // Per-request betas are appended and duplicates are sent once
+        let options = RequestOptions::new()
+            .with_beta("pdfs-2024-09-25")
+            .with_beta("token-efficient-tools-2025-02-19");
+        client
+            .get_message_completed_with_options(&body(), &options)
+            .await
+            .unwrap();
+    }
+    #[tokio::test]
+    async fn test_files_api_beta_header() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(|req: &Request| {
+                req.headers["anthropic-beta"] == "pdfs-2024-09-25,files-api-2025-04-14"
+            })
+            .respond_with(message_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let body = RequestBodyAnthropic {
+            messages: vec![Messages::new(
+                Role::User,
+                MessageContent::ContentArray(vec![
+                    ContentType::new_document(
+                        DocumentSource::new_file("file_011CNha8iCJcU1wXNR6q4V8w".to_string()),
+                        None,
+                        None,
+                    ),
+                    ContentType::new_text("Summarize this document.".to_string()),
+                ]),
+            )],
+            ..Default::default()
+        };
+        assert!(body.uses_files_api());
+        assert!(!self::body().uses_files_api());
+
+        let client =
+            AnthropicClient::new(Config::new("test-key".to_string(), server.uri())).unwrap();
+        let options = RequestOptions::new().with_beta("pdfs-2024-09-25");
+        client
+            .get_message_completed_with_options(&body, &options)
+            .await
+            .unwrap();
+    }
+    #[tokio::test]
+    async fn test_config_headers() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(header("x-team-id", "search"))
+            .and(header("x-gateway-auth", "Bearer gateway-token"))
+            .and(header("x-api-key", "test-key"))
+            .respond_with(message_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut config = Config::new("test-key".to_string(), server.uri());
+        config.set_headers(std::collections::HashMap::from([
+            ("x-team-id".to_string(), "search".to_string()),
+            (
+                "x-gateway-auth".to_string(),
+                "Bearer gateway-token".to_string(),
+            ),
+        ]));
+        let client = AnthropicClient::new(config).unwrap();
+        client.get_message_completed(&body()).await.unwrap();
+
+        let mut config = Config::new("test-key".to_string(), server.uri());
+        config.set_headers(std::collections::HashMap::from([(
+            "bad header".to_string(),
+            "value".to_string(),
+        )]));
+        assert!(AnthropicClient::new(config).is_err());
+    }
+    #[tokio::test]
+    async fn test_config_reserved_headers() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            // The headers of the client are sent once and keep their values
+            .and(|req: &Request| {
+                ["x-api-key", "content-type", "anthropic-version"]
+                    .iter()
+                    .all(|name| req.headers.get_all(*name).iter().count() == 1)
+                    && req.headers["x-api-key"] == "test-key"
+                    && req.headers["content-type"] == "application/json"
+                    && req.headers["x-team-id"] == "search"
+            })
+            .respond_with(message_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let headers = std::collections::HashMap::from([
+            ("X-Api-Key".to_string(), "other-key".to_string()),
+            ("content-type".to_string(), "text/plain".to_string()),
+            ("Anthropic-Version".to_string(), "2023-01-01".to_string()),
+            ("x-team-id".to_string(), "search".to_string()),
+        ]);
+        let mut config = Config::new("test-key".to_string(), server.uri());
+        config.set_headers(headers.clone());
+        let err = AnthropicClient::new(config).err().unwrap();
+        assert!(err.to_string().contains("set by the client"));
+
+        // An injected client skips the validation of new, the reserved headers are ignored
+        let mut config = Config::new("test-key".to_string(), server.uri());
+        config.set_headers(headers);
+        let client = AnthropicClient::with_client(config, reqwest::Client::new());
+        client.get_message_completed(&body()).await.unwrap();
+    }
+    #[tokio::test]
+    async fn test_options_on_other_endpoints() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages/count_tokens"))
+            .and(header("anthropic-beta", "token-counting-2024-11-01"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({"input_tokens": 12})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(header("anthropic-version", "2024-10-22"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": [],
+                "first_id": null,
+                "last_id": null,
+                "has_more": false
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/messages/batches"))
+            .and(header("anthropic-beta", "message-batches-2024-09-24"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": [],
+                "first_id": null,
+                "last_id": null,
+                "has_more": false
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client =
+            AnthropicClient::new(Config::new("test-key".to_string(), server.uri())).unwrap();
+        let res = client
+            .count_tokens_with_options(
+                &body(),
+                &RequestOptions::new().with_beta("token-counting-2024-11-01"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.input_tokens, 12);
+        let options = RequestOptions::new().with_version(Version::Custom("2024-10-22".to_string()));
+        assert!(client
+            .get_models_with_options(&options)
+            .await
+            .unwrap()
+            .data
+            .is_empty());
+        let options = RequestOptions::new().with_beta("message-batches-2024-09-24");
+        assert!(client
+            .list_batches_with_options(&options)
+            .await
+            .unwrap()
+            .data
+            .is_empty());
+    }
+}
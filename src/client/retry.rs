@@ -0,0 +1,177 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    RequestBuilder, Response, StatusCode,
+};
+
+use super::{error::AnthropicError, AnthropicClient};
+
+/// Default number of retries for requests failing with a retryable status
+pub const DEFAULT_MAX_RETRIES: u32 = 2;
+/// Default delay before the first retry, doubled on every attempt
+pub const DEFAULT_BASE_BACKOFF: Duration = Duration::from_millis(500);
+/// Upper bound for the computed backoff, `retry-after` is always honored
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+impl AnthropicClient {
+    /// Sends the request built by `build`, retrying on 429, 529 and other 5xx statuses.
+    ///
+    /// Waits for the `retry-after` header when present, otherwise uses jittered exponential
+    /// backoff starting at `base_backoff`. Any other status fails immediately.
+    pub(crate) async fn send_with_retry(
+        &self,
+        build: impl Fn() -> RequestBuilder,
+    ) -> Result<Response, AnthropicError> {
+        let mut attempt = 0;
+        loop {
+            let res = self.send(build()).await?;
+            let status = res.status();
+            if status.is_success() {
+                return Ok(res);
+            }
+            if attempt >= self.max_retries || !is_retryable(status) {
+                return Err(AnthropicError::from_response(res).await);
+            }
+            let delay = retry_after(res.headers())
+                .map(Duration::from_secs)
+                .unwrap_or_else(|| backoff(self.base_backoff, attempt));
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                status = status.as_u16(),
+                attempt,
+                delay_ms = delay.as_millis() as u64,
+                "retrying request"
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// 429 rate limit, 529 overloaded and any other server error are worth retrying
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Reads the `retry-after` header in seconds
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Exponential backoff with jitter, a random delay between 50% and 100% of `base * 2^attempt`
+fn backoff(base: Duration, attempt: u32) -> Duration {
+    let delay = base
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF);
+    let jitter = RandomState::new().build_hasher().finish() % 1000;
+    delay / 2 + (delay / 2).mul_f64(jitter as f64 / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{
+        test_support::{body, message_response},
+        Config,
+    };
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn client(server: &MockServer) -> AnthropicClient {
+        let mut config = Config::new("test-key".to_string(), server.uri());
+        config.set_retries(2, Duration::from_millis(1));
+        AnthropicClient::new(config).unwrap()
+    }
+
+    #[test]
+    fn test_backoff() {
+        let base = Duration::from_millis(100);
+        for attempt in 0..3 {
+            let delay = backoff(base, attempt);
+            let max = base * 2u32.pow(attempt);
+            assert!(delay >= max / 2 && delay <= max);
+        }
+        assert!(backoff(base, 20) <= MAX_BACKOFF);
+        assert!(is_retryable(StatusCode::from_u16(529).unwrap()));
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retryable(StatusCode::BAD_REQUEST));
+        assert!(!is_retryable(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_retry_on_overloaded() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(529))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(header("content-type", "application/json"))
+            .respond_with(message_response())
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let res = client(&server)
+            .get_message_completed(&body())
+            .await
+            .unwrap();
+        assert_eq!(res.id, "msg_1");
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_on_bad_request() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({
+                "type": "error",
+                "error": {"type": "invalid_request_error", "message": "bad"}
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = client(&server)
+            .get_message_completed(&body())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AnthropicError::InvalidRequest { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_retries_exhausted() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let err = client(&server)
+            .get_message_completed(&body())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AnthropicError::RateLimited {
+                retry_after: Some(0)
+            }
+        ));
+    }
+}
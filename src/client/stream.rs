@@ -0,0 +1,734 @@
+use std::collections::{HashMap, VecDeque};
+
+use futures::{stream, Stream, StreamExt};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    error::AnthropicError, options::RequestOptions, AnthropicClient, Citation, Container,
+    ContentType, RequestBodyAnthropic, ResponseBodyAnthropic, Role, ServerToolUsage, StopReason,
+    Usage,
+};
+
+/// Client implementation for streaming message completions.
+impl AnthropicClient {
+    /// Sends a message request with `"stream": true` and returns the server-sent events as a stream.
+    ///
+    /// # Arguments
+    /// * `body` - The request body, sent with `stream` set to `true`
+    ///
+    /// # Returns
+    /// A [`Stream`] yielding each [`StreamEvent`] as it arrives. Use [`StreamEvent::text_delta`]
+    /// to get the incremental text of `content_block_delta` events.
+    ///
+    /// # Errors
+    /// Returns an error if:
+    /// * The HTTP request fails
+    /// * The response status is not 200 after retrying 429, 529 and 5xx statuses
+    ///
+    /// Items of the stream are errors if the connection drops or an event cannot be parsed.
+    pub async fn get_message_completed_stream(
+        &self,
+        body: &RequestBodyAnthropic,
+    ) -> Result<impl Stream<Item = Result<StreamEvent, AnthropicError>>, AnthropicError> {
+        self.get_message_completed_stream_with_options(body, &RequestOptions::default())
+            .await
+    }
+    /// Same as `get_message_completed_stream` with per-request options such as beta headers or the version
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(model = %body.model, url, status))
+    )]
+    pub async fn get_message_completed_stream_with_options(
+        &self,
+        body: &RequestBodyAnthropic,
+        options: &RequestOptions,
+    ) -> Result<impl Stream<Item = Result<StreamEvent, AnthropicError>>, AnthropicError> {
+        self.check_body(body)?;
+        let body = RequestBodyAnthropic {
+            stream: Some(true),
+            ..body.clone()
+        };
+        let options = options.for_body(&body);
+        let res = self
+            .send_with_retry(|| self.post("messages", &options).json(&body))
+            .await?;
+        let state = (Some(res), SseParser::default(), VecDeque::new());
+        Ok(stream::unfold(
+            state,
+            |(mut res, mut parser, mut pending)| async move {
+                loop {
+                    if let Some(event) = pending.pop_front() {
+                        return Some((event, (res, parser, pending)));
+                    }
+                    let response = res.as_mut()?;
+                    match response.chunk().await {
+                        Ok(Some(chunk)) => pending.extend(parser.push(&chunk)),
+                        Ok(None) => {
+                            res = None;
+                            pending.extend(parser.finish());
+                        }
+                        Err(e) => {
+                            res = None;
+                            pending.push_back(Err(e.into()));
+                        }
+                    }
+                }
+            },
+        ))
+    }
+    /// Streams a message, passing every event to `on_event`, and returns the complete message.
+    ///
+    /// # Arguments
+    /// * `body` - The request body, sent with `stream` set to `true`
+    /// * `on_event` - Called with each event as it arrives, e.g. to display the text deltas
+    ///
+    /// # Returns
+    /// The [`ResponseBodyAnthropic`] assembled from the events by a [`MessageAccumulator`],
+    /// the same message `get_message_completed` would have returned.
+    ///
+    /// # Errors
+    /// Returns an error if:
+    /// * The request fails as in `get_message_completed_stream`
+    /// * The connection drops or an event cannot be parsed
+    /// * The API sends an `error` event
+    /// * The stream ends before the stop reason is sent, as [`AnthropicError::IncompleteStream`]
+    pub async fn get_message_completed_with_stream(
+        &self,
+        body: &RequestBodyAnthropic,
+        mut on_event: impl FnMut(&StreamEvent),
+    ) -> Result<ResponseBodyAnthropic, AnthropicError> {
+        let mut stream = Box::pin(self.get_message_completed_stream(body).await?);
+        let mut accumulator = MessageAccumulator::default();
+        while let Some(event) = stream.next().await {
+            let event = event?;
+            on_event(&event);
+            accumulator.push(&event)?;
+        }
+        accumulator.finish()
+    }
+}
+
+/// Assembles the events of a streamed message into the complete message
+#[derive(Debug, Default)]
+pub struct MessageAccumulator {
+    message: Option<StreamMessage>,
+    /// Partial JSON of the tool inputs by block index, parsed when the block stops
+    partial_json: HashMap<usize, String>,
+}
+impl MessageAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Applies an event to the message
+    /// Fails on `error` events and on tool inputs that are not valid JSON
+    pub fn push(&mut self, event: &StreamEvent) -> Result<(), AnthropicError> {
+        match event {
+            StreamEvent::MessageStart { message } => self.message = Some((**message).clone()),
+            StreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => {
+                if let Some(message) = &mut self.message {
+                    match message.content.get_mut(*index) {
+                        Some(block) => *block = content_block.clone(),
+                        None => message.content.push(content_block.clone()),
+                    }
+                }
+            }
+            StreamEvent::ContentBlockDelta { index, delta } => {
+                let block = self
+                    .message
+                    .as_mut()
+                    .and_then(|message| message.content.get_mut(*index));
+                match (block, delta) {
+                    (Some(ContentType::Text(block)), ContentDelta::TextDelta { text }) => {
+                        block.text.push_str(text)
+                    }
+                    (_, ContentDelta::InputJsonDelta { partial_json }) => self
+                        .partial_json
+                        .entry(*index)
+                        .or_default()
+                        .push_str(partial_json),
+                    (
+                        Some(ContentType::Thinking(block)),
+                        ContentDelta::ThinkingDelta { thinking },
+                    ) => block.thinking.push_str(thinking),
+                    (
+                        Some(ContentType::Thinking(block)),
+                        ContentDelta::SignatureDelta { signature },
+                    ) => block.signature = signature.clone(),
+                    (Some(ContentType::Text(block)), ContentDelta::CitationsDelta { citation }) => {
+                        block
+                            .citations
+                            .get_or_insert_with(Vec::new)
+                            .push(citation.clone())
+                    }
+                    _ => {}
+                }
+            }
+            StreamEvent::ContentBlockStop { index } => {
+                let Some(json) = self.partial_json.remove(index) else {
+                    return Ok(());
+                };
+                let input = if json.is_empty() {
+                    serde_json::json!({})
+                } else {
+                    serde_json::from_str(&json)?
+                };
+                match self
+                    .message
+                    .as_mut()
+                    .and_then(|message| message.content.get_mut(*index))
+                {
+                    Some(ContentType::ToolUse(block)) => block.input = input,
+                    Some(ContentType::ServerToolUse(block)) => block.input = input,
+                    _ => {}
+                }
+            }
+            StreamEvent::MessageDelta { delta, usage } => {
+                if let Some(message) = &mut self.message {
+                    message.stop_reason = delta.stop_reason.clone();
+                    message.stop_sequence = delta.stop_sequence.clone();
+                    usage.merge_into(&mut message.usage);
+                }
+            }
+            StreamEvent::Error { error } => {
+                let body = serde_json::json!({"type": "error", "error": error});
+                return Err(AnthropicError::from_parts(
+                    StatusCode::OK,
+                    None,
+                    &body.to_string(),
+                ));
+            }
+            StreamEvent::MessageStop | StreamEvent::Ping => {}
+        }
+        Ok(())
+    }
+    /// The complete message, fails if the stop reason has not been received
+    pub fn finish(self) -> Result<ResponseBodyAnthropic, AnthropicError> {
+        let message = self.message.ok_or(AnthropicError::IncompleteStream)?;
+        let stop_reason = message
+            .stop_reason
+            .ok_or(AnthropicError::IncompleteStream)?;
+        Ok(ResponseBodyAnthropic {
+            id: message.id,
+            model: message.model,
+            role: message.role,
+            stop_reason,
+            stop_sequence: message.stop_sequence,
+            message_type: message.message_type,
+            usage: message.usage,
+            content: message.content,
+            container: message.container,
+            extra: message.extra,
+        })
+    }
+}
+
+/// Events sent by the API when streaming a message
+/// See <https://docs.anthropic.com/en/api/messages-streaming>
+/// Event types added to the API after this version of the crate are skipped by the stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    MessageStart {
+        message: Box<StreamMessage>,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentType,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: ContentDelta,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    MessageDelta {
+        delta: MessageDelta,
+        usage: MessageDeltaUsage,
+    },
+    MessageStop,
+    Ping,
+    Error {
+        error: StreamError,
+    },
+}
+impl StreamEvent {
+    /// Returns the incremental text if this is a `content_block_delta` event carrying text
+    pub fn text_delta(&self) -> Option<&str> {
+        match self {
+            Self::ContentBlockDelta {
+                delta: ContentDelta::TextDelta { text },
+                ..
+            } => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// The message sent with the `message_start` event
+/// stop_reason is only known once the `message_delta` event arrives
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamMessage {
+    pub id: String,
+    pub model: String,
+    pub role: Role,
+    pub stop_reason: Option<StopReason>,
+    pub stop_sequence: Option<String>,
+    #[serde(rename = "type")]
+    pub message_type: String,
+    pub usage: Usage,
+    pub content: Vec<ContentType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<Container>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Incremental update of a content block
+/// CitationsDelta: A citation of the text block, sent before the text it supports
+/// Unknown: A delta type added to the API after this version of the crate, ignored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentDelta {
+    TextDelta {
+        text: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    ThinkingDelta {
+        thinking: String,
+    },
+    SignatureDelta {
+        signature: String,
+    },
+    CitationsDelta {
+        citation: Citation,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// Top level changes to the message sent with the `message_delta` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageDelta {
+    pub stop_reason: Option<StopReason>,
+    pub stop_sequence: Option<String>,
+}
+
+/// Cumulative usage sent with the `message_delta` event, fields that are not sent are unchanged
+/// extra: Fields added to the API after this version of the crate, kept as raw JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageDeltaUsage {
+    pub output_tokens: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_tokens: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_tokens: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_tool_use: Option<ServerToolUsage>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+impl MessageDeltaUsage {
+    /// Overwrites the usage of the message with every field of the delta
+    fn merge_into(&self, usage: &mut Usage) {
+        usage.output_tokens = self.output_tokens;
+        if let Some(input_tokens) = self.input_tokens {
+            usage.input_tokens = input_tokens;
+        }
+        if self.cache_creation_input_tokens.is_some() {
+            usage.cache_creation_input_tokens = self.cache_creation_input_tokens;
+        }
+        if self.cache_read_input_tokens.is_some() {
+            usage.cache_read_input_tokens = self.cache_read_input_tokens;
+        }
+        if self.server_tool_use.is_some() {
+            usage.server_tool_use = self.server_tool_use.clone();
+        }
+        usage.extra.extend(self.extra.clone());
+    }
+}
+
+/// Error sent with the `error` event, e.g. `overloaded_error`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamError {
+    #[serde(rename = "type")]
+    pub error_type: String,
+    pub message: String,
+}
+
+/// Incremental parser for the `event:`/`data:` framing of server-sent events
+#[derive(Debug, Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    done: bool,
+}
+impl SseParser {
+    /// Feeds a chunk of bytes and returns every event completed by it
+    fn push(&mut self, chunk: &[u8]) -> Vec<Result<StreamEvent, AnthropicError>> {
+        self.buffer.extend(chunk.iter().filter(|b| **b != b'\r'));
+        let mut events = vec![];
+        while let Some(pos) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let frame: Vec<u8> = self.buffer.drain(..pos + 2).collect();
+            if let Some(event) = self.parse_frame(&frame) {
+                events.push(event);
+            }
+        }
+        events
+    }
+    /// Parses whatever is left in the buffer once the connection is closed
+    fn finish(&mut self) -> Vec<Result<StreamEvent, AnthropicError>> {
+        let frame = std::mem::take(&mut self.buffer);
+        self.parse_frame(&frame).into_iter().collect()
+    }
+    fn parse_frame(&mut self, frame: &[u8]) -> Option<Result<StreamEvent, AnthropicError>> {
+        if self.done {
+            return None;
+        }
+        // Frames are split on ASCII newlines so a valid UTF-8 body is never cut inside a character
+        let frame = String::from_utf8_lossy(frame);
+        let mut data = vec![];
+        for line in frame.lines() {
+            if let Some(value) = line.strip_prefix("data:") {
+                data.push(value.strip_prefix(' ').unwrap_or(value));
+            }
+        }
+        if data.is_empty() {
+            return None;
+        }
+        let data = data.join("\n");
+        if data == "[DONE]" {
+            self.done = true;
+            return None;
+        }
+        match serde_json::from_str(&data) {
+            Ok(event) => Some(Ok(event)),
+            // Event types added to the API after this version of the crate are skipped
+            Err(_) if !is_known_event(&data) => None,
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
+/// Types of the events modeled by [`StreamEvent`]
+const KNOWN_EVENTS: [&str; 8] = [
+    "message_start",
+    "content_block_start",
+    "content_block_delta",
+    "content_block_stop",
+    "message_delta",
+    "message_stop",
+    "ping",
+    "error",
+];
+/// True if the data of a frame is a [`StreamEvent`] type, malformed JSON counts as known
+fn is_known_event(data: &str) -> bool {
+    match serde_json::from_str::<serde_json::Value>(data) {
+        Ok(value) => value["type"]
+            .as_str()
+            .is_some_and(|event_type| KNOWN_EVENTS.contains(&event_type)),
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{test_support, Messages};
+
+    const TRANSCRIPT: &str = "event: message_start
+data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"type\":\"message\",\"role\":\"assistant\",\"content\":[],\"model\":\"claude-3-5-sonnet-20241022\",\"stop_reason\":null,\"stop_sequence\":null,\"usage\":{\"input_tokens\":25,\"output_tokens\":1},\"future_field\":{\"enabled\":true}}}
+
+event: content_block_start
+data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}
+
+event: ping
+data: {\"type\": \"ping\"}
+
+event: content_block_delta
+data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello\"}}
+
+event: content_block_delta
+data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"!\"}}
+
+event: content_block_stop
+data: {\"type\":\"content_block_stop\",\"index\":0}
+
+event: message_delta
+data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\",\"stop_sequence\":null},\"usage\":{\"input_tokens\":25,\"output_tokens\":15,\"cache_creation_input_tokens\":0,\"cache_read_input_tokens\":1200,\"server_tool_use\":{\"web_search_requests\":2},\"future_usage\":3}}
+
+event: message_stop
+data: {\"type\":\"message_stop\"}
+
+data: [DONE]
+
+";
+
+    #[test]
+    fn test_parse_sse_transcript() {
+        let mut parser = SseParser::default();
+        let mut events = vec![];
+        // Feed the transcript in small chunks to exercise frames split across reads
+        for chunk in TRANSCRIPT.as_bytes().chunks(7) {
+            events.extend(parser.push(chunk));
+        }
+        events.extend(parser.finish());
+        let events: Vec<StreamEvent> = events.into_iter().map(|e| e.unwrap()).collect();
+
+        assert_eq!(events.len(), 8);
+        match &events[0] {
+            StreamEvent::MessageStart { message } => {
+                assert_eq!(message.id, "msg_1");
+                assert_eq!(message.stop_reason, None);
+                assert_eq!(message.usage.input_tokens, 25);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert!(matches!(events[2], StreamEvent::Ping));
+        let text: String = events.iter().filter_map(|e| e.text_delta()).collect();
+        assert_eq!(text, "Hello!");
+        match &events[6] {
+            StreamEvent::MessageDelta { delta, usage } => {
+                assert_eq!(delta.stop_reason, Some(StopReason::EndTurn));
+                assert_eq!(usage.output_tokens, 15);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert!(matches!(events[7], StreamEvent::MessageStop));
+    }
+
+    fn parse(transcript: &str) -> Vec<StreamEvent> {
+        let mut parser = SseParser::default();
+        let mut events = parser.push(transcript.as_bytes());
+        events.extend(parser.finish());
+        events.into_iter().map(|e| e.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_accumulate_message() {
+        let mut accumulator = MessageAccumulator::new();
+        for event in parse(TRANSCRIPT) {
+            accumulator.push(&event).unwrap();
+        }
+        let message = accumulator.finish().unwrap();
+        assert_eq!(message.id, "msg_1");
+        assert_eq!(message.text(), "Hello!");
+        assert_eq!(message.stop_reason, StopReason::EndTurn);
+        assert_eq!(message.usage.input_tokens, 25);
+        assert_eq!(message.usage.output_tokens, 15);
+        assert_eq!(message.usage.cache_creation_input_tokens, Some(0));
+        assert_eq!(message.usage.cache_read_input_tokens, Some(1200));
+        assert_eq!(
+            message
+                .usage
+                .server_tool_use
+                .as_ref()
+                .map(|usage| usage.web_search_requests),
+            Some(2)
+        );
+        assert_eq!(message.usage.extra["future_usage"], 3);
+        assert_eq!(message.extra["future_field"]["enabled"], true);
+
+        // The message is incomplete until the stop reason arrives
+        let mut accumulator = MessageAccumulator::new();
+        for event in parse(TRANSCRIPT).iter().take(5) {
+            accumulator.push(event).unwrap();
+        }
+        assert!(matches!(
+            accumulator.finish(),
+            Err(AnthropicError::IncompleteStream)
+        ));
+    }
+
+    #[test]
+    fn test_accumulate_tool_use() {
+        let transcript = "event: message_start
+data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_2\",\"type\":\"message\",\"role\":\"assistant\",\"content\":[],\"model\":\"claude-sonnet-4-20250514\",\"stop_reason\":null,\"stop_sequence\":null,\"usage\":{\"input_tokens\":400,\"output_tokens\":1}}}
+
+event: content_block_start
+data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"thinking\",\"thinking\":\"\",\"signature\":\"\"}}
+
+event: content_block_delta
+data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"thinking_delta\",\"thinking\":\"Need the weather.\"}}
+
+event: content_block_delta
+data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"signature_delta\",\"signature\":\"EqQBCgIYAhIM\"}}
+
+event: content_block_stop
+data: {\"type\":\"content_block_stop\",\"index\":0}
+
+event: content_block_start
+data: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"get_weather\",\"input\":{}}}
+
+event: content_block_delta
+data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"location\\\": \\\"San Fra\"}}
+
+event: content_block_delta
+data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"ncisco, CA\\\"}\"}}
+
+event: content_block_stop
+data: {\"type\":\"content_block_stop\",\"index\":1}
+
+event: message_delta
+data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\",\"stop_sequence\":null},\"usage\":{\"output_tokens\":89}}
+
+event: message_stop
+data: {\"type\":\"message_stop\"}
+
+";
+        let mut accumulator = MessageAccumulator::new();
+        for event in parse(transcript) {
+            accumulator.push(&event).unwrap();
+        }
+        let message = accumulator.finish().unwrap();
+        assert_eq!(message.stop_reason, StopReason::ToolUse);
+        assert_eq!(message.thinking(), "Need the weather.");
+        assert!(
+            matches!(&message.content[0], ContentType::Thinking(thinking) if thinking.signature == "EqQBCgIYAhIM")
+        );
+        let tool_uses = message.tool_uses();
+        assert_eq!(tool_uses.len(), 1);
+        assert_eq!(
+            tool_uses[0].input,
+            serde_json::json!({"location": "San Francisco, CA"})
+        );
+
+        let mut accumulator = MessageAccumulator::new();
+        let error = parse(
+            "event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n",
+        );
+        assert!(matches!(
+            accumulator.push(&error[0]),
+            Err(AnthropicError::Overloaded)
+        ));
+    }
+
+    #[test]
+    fn test_parse_sse_error_event() {
+        let mut parser = SseParser::default();
+        let events = parser.push(
+            b"event: error\r\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\r\n\r\n",
+        );
+        match events.into_iter().next().unwrap().unwrap() {
+            StreamEvent::Error { error } => {
+                assert_eq!(error.error_type, "overloaded_error");
+                assert_eq!(error.message, "Overloaded");
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_sse_unknown_event() {
+        let mut parser = SseParser::default();
+        let events = parser.push(
+            b"event: future_event\ndata: {\"type\":\"future_event\",\"value\":1}\n\nevent: ping\ndata: {\"type\":\"ping\"}\n\n",
+        );
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].as_ref().unwrap(), &StreamEvent::Ping);
+
+        // A malformed event of a known type is still an error
+        let events = parser.push(b"event: message_delta\ndata: {\"type\":\"message_delta\"}\n\n");
+        assert!(matches!(events[0], Err(AnthropicError::Serialization(_))));
+    }
+
+    #[tokio::test]
+    async fn test_stream_citations() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let transcript = "event: message_start
+data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_3\",\"type\":\"message\",\"role\":\"assistant\",\"content\":[],\"model\":\"claude-sonnet-4-20250514\",\"stop_reason\":null,\"stop_sequence\":null,\"usage\":{\"input_tokens\":610,\"output_tokens\":1}}}
+
+event: content_block_start
+data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\",\"citations\":[]}}
+
+event: content_block_delta
+data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"citations_delta\",\"citation\":{\"type\":\"char_location\",\"cited_text\":\"The grass is green.\",\"document_index\":0,\"document_title\":\"Facts\",\"start_char_index\":0,\"end_char_index\":20}}}
+
+event: content_block_delta
+data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"The grass is green.\"}}
+
+event: content_block_delta
+data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"future_delta\",\"value\":1}}
+
+event: content_block_stop
+data: {\"type\":\"content_block_stop\",\"index\":0}
+
+event: message_delta
+data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\",\"stop_sequence\":null},\"usage\":{\"output_tokens\":12}}
+
+event: message_stop
+data: {\"type\":\"message_stop\"}
+
+";
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(transcript),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = AnthropicClient::new(crate::client::Config::new(
+            "test-key".to_string(),
+            server.uri(),
+        ))
+        .unwrap();
+        let mut deltas = 0;
+        let message = client
+            .get_message_completed_with_stream(&test_support::body(), |event| {
+                if matches!(event, StreamEvent::ContentBlockDelta { .. }) {
+                    deltas += 1;
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(deltas, 3);
+        assert_eq!(message.text(), "The grass is green.");
+        match &message.content[0] {
+            ContentType::Text(text) => {
+                let citations = text.citations.as_ref().unwrap();
+                assert_eq!(citations.len(), 1);
+                assert_eq!(citations[0].cited_text(), "The grass is green.");
+                assert_eq!(citations[0].document_title(), Some("Facts"));
+            }
+            other => panic!("unexpected block {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_message_completed_stream() {
+        dotenvy::dotenv().ok();
+        let client = AnthropicClient::default().unwrap();
+        let messages = vec![Messages::new_user_message_prompt(
+            "What is the capital of France?".to_string(),
+        )];
+        let body = RequestBodyAnthropic {
+            messages,
+            ..Default::default()
+        };
+        let mut stream = Box::pin(client.get_message_completed_stream(&body).await.unwrap());
+        let mut text = String::new();
+        while let Some(event) = stream.next().await {
+            let event = event.unwrap();
+            if let Some(delta) = event.text_delta() {
+                text.push_str(delta);
+            }
+        }
+        println!("{}", text);
+        assert!(!text.is_empty());
+    }
+}
@@ -0,0 +1,25 @@
+use wiremock::ResponseTemplate;
+
+use super::{Messages, RequestBodyAnthropic};
+
+/// A successful response of the messages endpoint
+pub(crate) fn message_response() -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({
+        "id": "msg_1",
+        "model": "claude-sonnet-4-20250514",
+        "role": "assistant",
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "type": "message",
+        "usage": {"input_tokens": 10, "output_tokens": 5},
+        "content": [{"type": "text", "text": "Hi"}]
+    }))
+}
+
+/// A valid request body with a single user message
+pub(crate) fn body() -> RequestBodyAnthropic {
+    RequestBodyAnthropic {
+        messages: vec![Messages::new_user_message_prompt("Hi".to_string())],
+        ..Default::default()
+    }
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+
+use super::CacheControl;
+
+/// Definition of a tool the model may use
+/// name: The name of the tool, used by the model in `tool_use` blocks
+/// description: What the tool does, helps the model decide when to use it
+/// input_schema: JSON schema describing the input of the tool
+/// cache_control: Marks the tool definitions up to and including this one as cacheable
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tool {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub input_schema: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+impl Tool {
+    /// Create a new tool definition
+    /// name: The name of the tool
+    /// description: What the tool does
+    /// input_schema: JSON schema describing the input of the tool
+    pub fn new(name: String, description: String, input_schema: serde_json::Value) -> Self {
+        Self {
+            name,
+            description: Some(description),
+            input_schema,
+            cache_control: None,
+        }
+    }
+    /// Cache the tool definitions up to and including this one
+    /// Usually set on the last tool so the whole list is cached
+    pub fn with_cache_control(mut self) -> Self {
+        self.cache_control = Some(CacheControl::ephemeral());
+        self
+    }
+}
+
+/// How the model should use the provided tools
+/// Auto: The model decides whether to call a tool
+/// Any: The model must use one of the provided tools
+/// Tool: The model must use the named tool
+/// None: The model must not use any tools
+/// disable_parallel_tool_use: When true the model calls at most one tool per turn
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToolChoice {
+    Auto {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        disable_parallel_tool_use: Option<bool>,
+    },
+    Any {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        disable_parallel_tool_use: Option<bool>,
+    },
+    Tool {
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        disable_parallel_tool_use: Option<bool>,
+    },
+    None,
+}
+impl ToolChoice {
+    /// Let the model decide whether to call a tool
+    pub fn auto() -> Self {
+        Self::Auto {
+            disable_parallel_tool_use: None,
+        }
+    }
+    /// Force the model to use one of the provided tools
+    pub fn any() -> Self {
+        Self::Any {
+            disable_parallel_tool_use: None,
+        }
+    }
+    /// Force the model to use the named tool
+    pub fn tool(name: impl Into<String>) -> Self {
+        Self::Tool {
+            name: name.into(),
+            disable_parallel_tool_use: None,
+        }
+    }
+    /// Prevent the model from calling several tools in one turn
+    /// Has no effect on `ToolChoice::None`
+    pub fn with_disable_parallel_tool_use(mut self, disable: bool) -> Self {
+        match &mut self {
+            Self::Auto {
+                disable_parallel_tool_use,
+            }
+            | Self::Any {
+                disable_parallel_tool_use,
+            }
+            | Self::Tool {
+                disable_parallel_tool_use,
+                ..
+            } => *disable_parallel_tool_use = Some(disable),
+            Self::None => {}
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_serialization() {
+        let tool = Tool::new(
+            "get_weather".to_string(),
+            "Get the current weather in a given location".to_string(),
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "location": {
+                        "type": "string",
+                        "description": "The city and state, e.g. San Francisco, CA"
+                    }
+                },
+                "required": ["location"]
+            }),
+        );
+        assert_eq!(
+            serde_json::to_value(&tool).unwrap(),
+            serde_json::json!({
+                "name": "get_weather",
+                "description": "Get the current weather in a given location",
+                "input_schema": {
+                    "type": "object",
+                    "properties": {
+                        "location": {
+                            "type": "string",
+                            "description": "The city and state, e.g. San Francisco, CA"
+                        }
+                    },
+                    "required": ["location"]
+                }
+            })
+        );
+    }
+    #[test]
+    fn test_tool_choice_serialization() {
+        assert_eq!(
+            serde_json::to_value(ToolChoice::auto()).unwrap(),
+            serde_json::json!({"type": "auto"})
+        );
+        assert_eq!(
+            serde_json::to_value(ToolChoice::any()).unwrap(),
+            serde_json::json!({"type": "any"})
+        );
+        assert_eq!(
+            serde_json::to_value(ToolChoice::tool("get_weather")).unwrap(),
+            serde_json::json!({"type": "tool", "name": "get_weather"})
+        );
+        assert_eq!(
+            serde_json::to_value(ToolChoice::None).unwrap(),
+            serde_json::json!({"type": "none"})
+        );
+    }
+    #[test]
+    fn test_tool_choice_round_trip() {
+        for (json, choice) in [
+            (r#"{"type":"auto"}"#, ToolChoice::auto()),
+            (r#"{"type":"any"}"#, ToolChoice::any()),
+            (
+                r#"{"type":"tool","name":"get_weather"}"#,
+                ToolChoice::tool("get_weather"),
+            ),
+            (r#"{"type":"none"}"#, ToolChoice::None),
+        ] {
+            assert_eq!(serde_json::to_string(&choice).unwrap(), json);
+            assert_eq!(serde_json::from_str::<ToolChoice>(json).unwrap(), choice);
+        }
+        assert!(serde_json::from_str::<ToolChoice>(r#"{"type":"tool"}"#).is_err());
+    }
+    #[test]
+    fn test_disable_parallel_tool_use() {
+        assert_eq!(
+            serde_json::to_value(ToolChoice::auto().with_disable_parallel_tool_use(true)).unwrap(),
+            serde_json::json!({"type": "auto", "disable_parallel_tool_use": true})
+        );
+        assert_eq!(
+            serde_json::to_value(ToolChoice::any().with_disable_parallel_tool_use(true)).unwrap(),
+            serde_json::json!({"type": "any", "disable_parallel_tool_use": true})
+        );
+        assert_eq!(
+            serde_json::to_value(
+                ToolChoice::tool("get_weather").with_disable_parallel_tool_use(false)
+            )
+            .unwrap(),
+            serde_json::json!({"type": "tool", "name": "get_weather", "disable_parallel_tool_use": false})
+        );
+        assert_eq!(
+            serde_json::to_value(ToolChoice::None.with_disable_parallel_tool_use(true)).unwrap(),
+            serde_json::json!({"type": "none"})
+        );
+        let choice: ToolChoice =
+            serde_json::from_str(r#"{"type":"auto","disable_parallel_tool_use":true}"#).unwrap();
+        assert_eq!(
+            choice,
+            ToolChoice::auto().with_disable_parallel_tool_use(true)
+        );
+    }
+    #[test]
+    fn test_tool_cache_control() {
+        let tool = Tool::new(
+            "get_weather".to_string(),
+            "Get the current weather".to_string(),
+            serde_json::json!({"type": "object"}),
+        );
+        assert!(serde_json::to_value(&tool)
+            .unwrap()
+            .get("cache_control")
+            .is_none());
+        assert_eq!(
+            serde_json::to_value(tool.with_cache_control()).unwrap(),
+            serde_json::json!({
+                "name": "get_weather",
+                "description": "Get the current weather",
+                "input_schema": {"type": "object"},
+                "cache_control": {"type": "ephemeral"}
+            })
+        );
+    }
+}
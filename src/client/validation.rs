@@ -0,0 +1,157 @@
+use core::fmt;
+
+/// Error returned when a request fails local validation, before anything is sent
+/// InvalidValue: A field holds a value the API would reject
+/// InvalidMessage: The message at `index` of `messages` would be rejected
+/// InvalidContentBlock: The block at `block` of the content of the message at `message` would be rejected
+/// Multiple: Several problems were found, in the order they were checked
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    InvalidValue {
+        field: &'static str,
+        value: String,
+        reason: String,
+    },
+    InvalidMessage {
+        index: usize,
+        reason: String,
+    },
+    InvalidContentBlock {
+        message: usize,
+        block: usize,
+        reason: String,
+    },
+    Multiple(Vec<ValidationError>),
+}
+impl ValidationError {
+    pub fn invalid_value(field: &'static str, value: impl fmt::Display, reason: &str) -> Self {
+        Self::InvalidValue {
+            field,
+            value: value.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+    pub fn invalid_message(index: usize, reason: &str) -> Self {
+        Self::InvalidMessage {
+            index,
+            reason: reason.to_string(),
+        }
+    }
+    pub fn invalid_content_block(message: usize, block: usize, reason: &str) -> Self {
+        Self::InvalidContentBlock {
+            message,
+            block,
+            reason: reason.to_string(),
+        }
+    }
+    /// Every problem, whether one or several were found
+    pub fn errors(&self) -> Vec<&ValidationError> {
+        match self {
+            Self::Multiple(errors) => errors.iter().collect(),
+            error => vec![error],
+        }
+    }
+}
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidValue {
+                field,
+                value,
+                reason,
+            } => write!(f, "Invalid {} {}: {}", field, value, reason),
+            Self::InvalidMessage { index, reason } => {
+                write!(f, "Invalid message at index {}: {}", index, reason)
+            }
+            Self::InvalidContentBlock {
+                message,
+                block,
+                reason,
+            } => write!(
+                f,
+                "Invalid content block {} of message {}: {}",
+                block, message, reason
+            ),
+            Self::Multiple(errors) => {
+                write!(f, "{} validation errors: ", errors.len())?;
+                for (i, error) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+impl std::error::Error for ValidationError {}
+
+/// Collects every problem found while validating a request
+/// Checks of new fields push their errors here so they are all reported together
+#[derive(Debug, Default)]
+pub(crate) struct Validator {
+    errors: Vec<ValidationError>,
+}
+impl Validator {
+    pub(crate) fn push(&mut self, error: ValidationError) {
+        self.errors.push(error);
+    }
+    pub(crate) fn check(&mut self, result: Result<(), ValidationError>) {
+        if let Err(error) = result {
+            self.push(error);
+        }
+    }
+    /// Ok when nothing was found, the error itself for one problem, `Multiple` otherwise
+    pub(crate) fn finish(mut self) -> Result<(), ValidationError> {
+        match self.errors.len() {
+            0 => Ok(()),
+            1 => Err(self.errors.remove(0)),
+            _ => Err(ValidationError::Multiple(self.errors)),
+        }
+    }
+}
+
+/// Checks that an optional sampling parameter lies within `[min, max]`
+/// NaN is always rejected
+pub(crate) fn check_range(
+    field: &'static str,
+    value: Option<f32>,
+    min: f32,
+    max: f32,
+) -> Result<(), ValidationError> {
+    match value {
+        Some(value) if !(min..=max).contains(&value) => Err(ValidationError::invalid_value(
+            field,
+            value,
+            &format!("must be between {:.1} and {:.1}", min, max),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_range() {
+        assert!(check_range("temperature", None, 0.0, 1.0).is_ok());
+        assert!(check_range("temperature", Some(0.0), 0.0, 1.0).is_ok());
+        assert!(check_range("temperature", Some(1.0), 0.0, 1.0).is_ok());
+        assert_eq!(
+            check_range("temperature", Some(7.3), 0.0, 1.0),
+            Err(ValidationError::InvalidValue {
+                field: "temperature",
+                value: "7.3".to_string(),
+                reason: "must be between 0.0 and 1.0".to_string(),
+            })
+        );
+        assert!(check_range("temperature", Some(-0.1), 0.0, 1.0).is_err());
+        let err = check_range("temperature", Some(f32::NAN), 0.0, 1.0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid temperature NaN: must be between 0.0 and 1.0"
+        );
+    }
+}